@@ -0,0 +1,124 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A blocking client for interacting with a Percas cluster.
+//!
+//! The blocking [`Client`] runs the async [`crate::Client`] on an internal runtime, so it routes,
+//! retries and caches exactly the same way. It must not be used within an async context.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use percas_client::ClientBuilder;
+//!
+//! let client = ClientBuilder::new("http://percas-data:8080", "http://percas-ctrl:8081")
+//!     .build_blocking()
+//!     .unwrap();
+//! client.put("key", b"value").unwrap();
+//! assert_eq!(client.get("key").unwrap().unwrap(), b"value");
+//! ```
+
+use reqwest::Body;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::Error;
+use crate::NearCacheStats;
+use crate::RequestOptions;
+use crate::protos::Version;
+
+/// A blocking client for interacting with a Percas cluster.
+///
+/// Create it with [`ClientBuilder::build_blocking`](crate::ClientBuilder::build_blocking).
+pub struct Client {
+    // dropped before the runtime it spawns the route table refresh on
+    inner: crate::Client,
+    rt: tokio::runtime::Runtime,
+}
+
+impl Client {
+    pub(crate) fn new(inner: crate::Client) -> Result<Self, Error> {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("percas-client")
+            .enable_all()
+            .build()
+            .map_err(|source| Error::Runtime { source })?;
+        Ok(Self { inner, rt })
+    }
+
+    /// Get the value associated with the given key.
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.rt.block_on(self.inner.get(key))
+    }
+
+    /// Get the value associated with the given key, with options for this call.
+    pub fn get_with(&self, key: &str, options: &RequestOptions) -> Result<Option<Vec<u8>>, Error> {
+        self.rt.block_on(self.inner.get_with(key, options))
+    }
+
+    /// Set the value associated with the given key.
+    pub fn put(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.rt.block_on(self.inner.put(key, value))
+    }
+
+    /// Set the value associated with the given key, taking ownership of the value.
+    ///
+    /// See [`crate::Client::put_owned`].
+    pub fn put_owned<T: Into<Body>>(&self, key: &str, value: T) -> Result<(), Error> {
+        self.rt.block_on(self.inner.put_owned(key, value))
+    }
+
+    /// Set the value associated with the given key, with options for this call.
+    pub fn put_with<T: Into<Body>>(
+        &self,
+        key: &str,
+        value: T,
+        options: &RequestOptions,
+    ) -> Result<(), Error> {
+        self.rt.block_on(self.inner.put_with(key, value, options))
+    }
+
+    /// Get the value associated with the given key, decoded as written by
+    /// [`Client::put_typed`].
+    pub fn get_typed<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Error> {
+        self.rt.block_on(self.inner.get_typed(key))
+    }
+
+    /// Set the value associated with the given key to a value encoded with the codec of this
+    /// client.
+    pub fn put_typed<T: Serialize + ?Sized>(&self, key: &str, value: &T) -> Result<(), Error> {
+        self.rt.block_on(self.inner.put_typed(key, value))
+    }
+
+    /// Delete the value associated with the given key.
+    pub fn delete(&self, key: &str) -> Result<(), Error> {
+        self.rt.block_on(self.inner.delete(key))
+    }
+
+    /// Delete the value associated with the given key, with options for this call.
+    pub fn delete_with(&self, key: &str, options: &RequestOptions) -> Result<(), Error> {
+        self.rt.block_on(self.inner.delete_with(key, options))
+    }
+
+    /// Get the version of the Percas server.
+    pub fn version(&self) -> Result<Version, Error> {
+        self.rt.block_on(self.inner.version())
+    }
+
+    /// Counters of the near cache, if it is enabled.
+    pub fn near_cache_stats(&self) -> Option<NearCacheStats> {
+        self.inner.near_cache_stats()
+    }
+}
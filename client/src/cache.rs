@@ -0,0 +1,328 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Request header asking the server for the `ETag` of a value read, so that it can be cached.
+pub(crate) const ETAG_HEADER: &str = "x-percas-etag";
+
+/// How a [`Client`](crate::Client) caches values in process.
+///
+/// The near cache keeps recently read values in an LRU bounded by the total size of keys and
+/// values. An entry older than the max age is revalidated with the server before use, which
+/// only transfers the value again if it has changed.
+///
+/// Values written or deleted by this client are invalidated, but writes from other clients are
+/// only observed once the entry expires, so the max age bounds how stale a read may be.
+#[derive(Debug, Clone)]
+pub struct NearCachePolicy {
+    capacity: usize,
+    max_age: Duration,
+}
+
+impl NearCachePolicy {
+    /// Cache up to `capacity` bytes of keys and values, fresh for 10s by default.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            max_age: Duration::from_secs(10),
+        }
+    }
+
+    /// Set how long an entry is used before it is revalidated with the server.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+}
+
+/// Counters of a near cache, see [`Client::near_cache_stats`](crate::Client::near_cache_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct NearCacheStats {
+    /// Reads served from a fresh entry.
+    pub hits: u64,
+    /// Reads that found no entry.
+    pub misses: u64,
+    /// Reads that found an expired entry and revalidated it with the server.
+    pub revalidations: u64,
+    /// Revalidations that the server confirmed, so the value was not transferred again.
+    pub not_modified: u64,
+    /// Entries evicted to stay within the capacity.
+    pub evictions: u64,
+    /// The number of entries.
+    pub entries: u64,
+    /// The total size of keys and values.
+    pub size: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: Vec<u8>,
+    etag: Option<String>,
+    fetched_at: Instant,
+    // position in the recency order
+    tick: u64,
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    entries: HashMap<String, Entry>,
+    // tick to key, from the least recently used
+    recency: BTreeMap<u64, String>,
+    next_tick: u64,
+    size: usize,
+}
+
+impl Lru {
+    fn touch(&mut self, key: &str) -> Option<&mut Entry> {
+        let tick = self.next_tick;
+        let entry = self.entries.get_mut(key)?;
+        let key = self
+            .recency
+            .remove(&entry.tick)
+            .expect("entries are ordered");
+        entry.tick = tick;
+        self.recency.insert(tick, key);
+        self.next_tick += 1;
+        Some(entry)
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.recency.remove(&entry.tick);
+        self.size -= key.len() + entry.value.len();
+        Some(entry)
+    }
+}
+
+/// The outcome of looking up a key in the near cache.
+#[derive(Debug)]
+pub(crate) enum Lookup {
+    /// A fresh value; no request is needed.
+    Fresh(Vec<u8>),
+    /// An expired value to revalidate with its entity tag.
+    Stale { value: Vec<u8>, etag: String },
+    /// No usable value.
+    Miss,
+}
+
+#[derive(Debug)]
+pub(crate) struct NearCache {
+    policy: NearCachePolicy,
+    lru: Mutex<Lru>,
+    // bumped on every invalidation, so that reads racing with a write do not cache old values
+    invalidations: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    revalidations: AtomicU64,
+    not_modified: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl NearCache {
+    pub(crate) fn new(policy: NearCachePolicy) -> Self {
+        Self {
+            policy,
+            lru: Mutex::new(Lru::default()),
+            invalidations: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            revalidations: AtomicU64::new(0),
+            not_modified: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// A token to pass to [`NearCache::insert`], taken before the value is read from the server.
+    pub(crate) fn generation(&self) -> u64 {
+        self.invalidations.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn lookup(&self, key: &str) -> Lookup {
+        let mut lru = self.lru.lock().unwrap();
+        let Some(entry) = lru.touch(key) else {
+            drop(lru);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return Lookup::Miss;
+        };
+
+        if entry.fetched_at.elapsed() < self.policy.max_age {
+            let value = entry.value.clone();
+            drop(lru);
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Lookup::Fresh(value);
+        }
+
+        match &entry.etag {
+            Some(etag) => {
+                let lookup = Lookup::Stale {
+                    value: entry.value.clone(),
+                    etag: etag.clone(),
+                };
+                drop(lru);
+                self.revalidations.fetch_add(1, Ordering::Relaxed);
+                lookup
+            }
+            None => {
+                lru.remove(key);
+                drop(lru);
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                Lookup::Miss
+            }
+        }
+    }
+
+    /// Mark an expired entry fresh again after the server confirmed it is unchanged.
+    pub(crate) fn confirm(&self, key: &str, etag: &str) {
+        self.not_modified.fetch_add(1, Ordering::Relaxed);
+        let mut lru = self.lru.lock().unwrap();
+        if let Some(entry) = lru.entries.get_mut(key)
+            && entry.etag.as_deref() == Some(etag)
+        {
+            entry.fetched_at = Instant::now();
+        }
+    }
+
+    /// Cache a value read from the server, unless the key was invalidated since `generation`.
+    pub(crate) fn insert(&self, key: &str, value: Vec<u8>, etag: Option<String>, generation: u64) {
+        let size = key.len() + value.len();
+        if size > self.policy.capacity {
+            return;
+        }
+
+        let mut lru = self.lru.lock().unwrap();
+        if self.generation() != generation {
+            return;
+        }
+        lru.remove(key);
+
+        let mut evictions = 0;
+        while lru.size + size > self.policy.capacity {
+            let Some((_, victim)) = lru.recency.pop_first() else {
+                break;
+            };
+            let entry = lru.entries.remove(&victim).expect("entries are ordered");
+            lru.size -= victim.len() + entry.value.len();
+            evictions += 1;
+        }
+
+        let tick = lru.next_tick;
+        lru.next_tick += 1;
+        lru.recency.insert(tick, key.to_string());
+        lru.entries.insert(
+            key.to_string(),
+            Entry {
+                value,
+                etag,
+                fetched_at: Instant::now(),
+                tick,
+            },
+        );
+        lru.size += size;
+        drop(lru);
+        self.evictions.fetch_add(evictions, Ordering::Relaxed);
+    }
+
+    /// Drop the entry of a key written or deleted by this client.
+    pub(crate) fn invalidate(&self, key: &str) {
+        let mut lru = self.lru.lock().unwrap();
+        self.invalidations.fetch_add(1, Ordering::AcqRel);
+        lru.remove(key);
+    }
+
+    pub(crate) fn stats(&self) -> NearCacheStats {
+        let (entries, size) = {
+            let lru = self.lru.lock().unwrap();
+            (lru.entries.len() as u64, lru.size as u64)
+        };
+        NearCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            revalidations: self.revalidations.load(Ordering::Relaxed),
+            not_modified: self.not_modified.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries,
+            size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fresh(cache: &NearCache, key: &str) -> Option<Vec<u8>> {
+        match cache.lookup(key) {
+            Lookup::Fresh(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = NearCache::new(NearCachePolicy::new(8));
+        cache.insert("a", b"11".to_vec(), None, cache.generation());
+        cache.insert("b", b"22".to_vec(), None, cache.generation());
+        assert_eq!(fresh(&cache, "a").unwrap(), b"11");
+
+        // "b" is the least recently used
+        cache.insert("c", b"33".to_vec(), None, cache.generation());
+        assert_eq!(fresh(&cache, "b"), None);
+        assert_eq!(fresh(&cache, "a").unwrap(), b"11");
+        assert_eq!(fresh(&cache, "c").unwrap(), b"33");
+
+        // larger than the capacity
+        cache.insert("d", b"123456789".to_vec(), None, cache.generation());
+        assert_eq!(fresh(&cache, "d"), None);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (3, 2, 1));
+        assert_eq!((stats.entries, stats.size), (2, 6));
+    }
+
+    #[test]
+    fn test_invalidate_and_revalidate() {
+        let cache = NearCache::new(NearCachePolicy::new(64).with_max_age(Duration::ZERO));
+
+        // a read that started before a write must not cache the old value
+        let generation = cache.generation();
+        cache.invalidate("a");
+        cache.insert("a", b"old".to_vec(), Some("\"1\"".to_string()), generation);
+        assert!(matches!(cache.lookup("a"), Lookup::Miss));
+
+        cache.insert(
+            "a",
+            b"new".to_vec(),
+            Some("\"2\"".to_string()),
+            cache.generation(),
+        );
+        let Lookup::Stale { value, etag } = cache.lookup("a") else {
+            panic!("expired entries with an etag are revalidated");
+        };
+        assert_eq!((value.as_slice(), etag.as_str()), (&b"new"[..], "\"2\""));
+        cache.confirm("a", &etag);
+
+        // expired entries without an etag are dropped
+        cache.insert("b", b"value".to_vec(), None, cache.generation());
+        assert!(matches!(cache.lookup("b"), Lookup::Miss));
+        assert_eq!(cache.stats().entries, 1);
+    }
+}
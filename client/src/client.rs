@@ -12,34 +12,71 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::RwLock;
+use std::pin::pin;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 use std::time::Instant;
 
+use bytes::Bytes;
+use fastrace::Span;
+use fastrace::future::FutureExt as _;
+use fastrace::local::LocalSpan;
 use fastrace_reqwest::traceparent_headers;
+use futures_util::TryStream;
+use futures_util::future::Either;
+use futures_util::future::select;
 use reqwest::Body;
+use reqwest::Method;
 use reqwest::StatusCode;
 use reqwest::Url;
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::header::ETAG;
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderValue;
+use reqwest::header::IF_NONE_MATCH;
+use reqwest::header::LOCATION;
 use reqwest::redirect::Policy;
-use serde::Deserialize;
-use uuid::Uuid;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio::io::AsyncRead;
+use tokio::task::JoinHandle;
+use tokio_util::io::ReaderStream;
 
+use crate::Codec;
 use crate::Error;
+use crate::HedgePolicy;
+use crate::NearCachePolicy;
+use crate::NearCacheStats;
+use crate::RetryPolicy;
+use crate::ValueStream;
+use crate::cache::ETAG_HEADER;
+use crate::cache::Lookup;
+use crate::cache::NearCache;
+use crate::codec::CODEC_HEADER;
+use crate::hedge::LatencyTracker;
+use crate::metrics;
 use crate::protos::Version;
-use crate::route::RouteTable;
+use crate::router::RefreshOptions;
+use crate::router::Router;
 
-const UPDATE_ROUTE_TABLE_INTERVAL: Duration = Duration::from_secs(10);
-
-fn make_opaque_error(msg: impl ToString) -> Error {
-    Error::Opaque(msg.to_string())
-}
+const ROUTE_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+const ROUTE_REFRESH_JITTER: Duration = Duration::from_secs(1);
 
 /// A builder for creating a `Client`.
 #[derive(Debug, Clone)]
 pub struct ClientBuilder {
     data_url: String,
-    ctrl_url: String,
+    ctrl_urls: Vec<String>,
     client: Option<reqwest::Client>,
+    retry: RetryPolicy,
+    hedge: Option<HedgePolicy>,
+    near_cache: Option<NearCachePolicy>,
+    timeout: Option<Duration>,
+    codec: Codec,
+    refresh_interval: Duration,
+    refresh_jitter: Duration,
+    watch_members: bool,
 }
 
 impl ClientBuilder {
@@ -57,103 +94,291 @@ impl ClientBuilder {
     pub fn new(data_url: impl Into<String>, ctrl_url: impl Into<String>) -> Self {
         Self {
             data_url: data_url.into(),
-            ctrl_url: ctrl_url.into(),
+            ctrl_urls: vec![ctrl_url.into()],
             client: None,
+            retry: RetryPolicy::default(),
+            hedge: None,
+            near_cache: None,
+            timeout: None,
+            codec: Codec::default(),
+            refresh_interval: ROUTE_REFRESH_INTERVAL,
+            refresh_jitter: ROUTE_REFRESH_JITTER,
+            watch_members: false,
         }
     }
 
+    /// Add a control server url to fetch the route table from when the others are unreachable.
+    ///
+    /// Once a route table is fetched, the control servers of its members are tried as well.
+    pub fn add_ctrl_url(mut self, ctrl_url: impl Into<String>) -> Self {
+        self.ctrl_urls.push(ctrl_url.into());
+        self
+    }
+
+    /// Set the interval of refreshing the route table in the background. Defaults to 10s.
+    pub fn route_refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = interval;
+        self
+    }
+
+    /// Set the maximal random delay added to each refresh interval, so that many clients do not
+    /// refresh at the same time. Defaults to 1s.
+    pub fn route_refresh_jitter(mut self, jitter: Duration) -> Self {
+        self.refresh_jitter = jitter;
+        self
+    }
+
+    /// Set whether to watch the cluster membership, so that ring changes are picked up as soon as
+    /// the control server reports them. Disabled by default.
+    ///
+    /// Control servers that cannot hold a watch are detected, and the client falls back to
+    /// refreshing at the interval.
+    pub fn watch_members(mut self, watch_members: bool) -> Self {
+        self.watch_members = watch_members;
+        self
+    }
+
     /// Set a custom HTTP client. If not set, a default client will be used.
     pub fn http_client(mut self, client: reqwest::Client) -> Self {
         self.client = Some(client);
         self
     }
 
+    /// Set the retry policy. If not set, [`RetryPolicy::default`] is used.
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Enable hedged reads with the given policy. Reads are not hedged by default.
+    pub fn hedge_policy(mut self, hedge: HedgePolicy) -> Self {
+        self.hedge = Some(hedge);
+        self
+    }
+
+    /// Enable the in-process near cache of values with the given policy. Disabled by default.
+    pub fn near_cache(mut self, near_cache: NearCachePolicy) -> Self {
+        self.near_cache = Some(near_cache);
+        self
+    }
+
+    /// Set the default deadline of a call, including all its retries. No deadline by default.
+    ///
+    /// It can be overridden per call with [`RequestOptions::with_timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the codec of [`Client::put_typed`]. Defaults to uncompressed JSON.
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Build a [blocking client](crate::blocking::Client).
+    #[cfg(feature = "blocking")]
+    pub fn build_blocking(self) -> Result<crate::blocking::Client, Error> {
+        crate::blocking::Client::new(self.build()?)
+    }
+
     /// Build the client.
+    ///
+    /// The route table is refreshed by a task spawned on the Tokio runtime of the first call.
     pub fn build(self) -> Result<Client, Error> {
         let Self {
             data_url,
-            ctrl_url,
+            ctrl_urls,
             client,
+            retry,
+            hedge,
+            near_cache,
+            timeout,
+            codec,
+            refresh_interval,
+            refresh_jitter,
+            watch_members,
         } = self;
 
-        let data_url = Url::parse(&data_url).map_err(make_opaque_error)?;
-        let ctrl_url = Url::parse(&ctrl_url).map_err(make_opaque_error)?;
+        let data_url = Url::parse(&data_url).map_err(|err| Error::invalid_url(&data_url, err))?;
+        let ctrl_urls = ctrl_urls
+            .iter()
+            .map(|url| Url::parse(url).map_err(|err| Error::invalid_url(url, err)))
+            .collect::<Result<Vec<_>, _>>()?;
         let client = match client {
             Some(client) => client,
             None => reqwest::ClientBuilder::new()
                 .no_proxy()
                 .redirect(Policy::limited(2))
                 .build()
-                .map_err(make_opaque_error)?,
+                .map_err(|source| Error::Build { source })?,
         };
 
-        // force an initial route table update on first use
-        let last_updated = Instant::now() - UPDATE_ROUTE_TABLE_INTERVAL - Duration::from_secs(1);
+        let router = Router::new(
+            client.clone(),
+            ctrl_urls,
+            RefreshOptions {
+                interval: refresh_interval,
+                jitter: refresh_jitter,
+                watch: watch_members,
+            },
+        );
         Ok(Client {
             client,
             data_url,
-            ctrl_url,
-            last_updated: RwLock::new(last_updated),
-            route_table: RwLock::new(None),
+            retry,
+            latency: hedge.map(LatencyTracker::new),
+            near_cache: near_cache.map(NearCache::new),
+            timeout,
+            codec,
+            router: Arc::new(router),
+            refresher: Mutex::new(None),
         })
     }
 }
 
+/// Options of a single call, see [`Client::get_with`], [`Client::put_with`] and
+/// [`Client::delete_with`].
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    timeout: Option<Duration>,
+    bypass_near_cache: bool,
+}
+
+impl RequestOptions {
+    /// Create options that inherit everything from the client.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the deadline of this call, including all its retries.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set whether a read skips the near cache and goes to the server. The cached entry is
+    /// still refreshed with the value read.
+    pub fn with_bypass_near_cache(mut self, bypass_near_cache: bool) -> Self {
+        self.bypass_near_cache = bypass_near_cache;
+        self
+    }
+}
+
+/// The result of reading a key from the server.
+enum Fetched {
+    Value {
+        value: Vec<u8>,
+        etag: Option<String>,
+    },
+    NotFound,
+    // the cached value with the given entity tag is still current
+    NotModified,
+}
+
 /// A client for interacting with a Percas cluster.
 pub struct Client {
     client: reqwest::Client,
     data_url: Url,
-    ctrl_url: Url,
-    last_updated: RwLock<Instant>,
-    route_table: RwLock<Option<RouteTable>>,
+    retry: RetryPolicy,
+    // present if reads are hedged
+    latency: Option<LatencyTracker>,
+    near_cache: Option<NearCache>,
+    timeout: Option<Duration>,
+    codec: Codec,
+    router: Arc<Router>,
+    // the background task refreshing the route table, spawned on first use and again whenever it
+    // ended with the runtime it was spawned on
+    refresher: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let refresher = self.refresher.get_mut().unwrap_or_else(|e| e.into_inner());
+        if let Some(refresher) = refresher.take() {
+            refresher.abort();
+        }
+    }
 }
 
 impl Client {
     /// Get the value associated with the given key.
     pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
-        self.update_route_table_if_needed().await?;
+        self.get_with(key, &RequestOptions::default()).await
+    }
 
-        let url = self.route(key).join(key).map_err(make_opaque_error)?;
+    /// Get the value associated with the given key, with options for this call.
+    pub async fn get_with(
+        &self,
+        key: &str,
+        options: &RequestOptions,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let Some(near_cache) = &self.near_cache else {
+            return match self.fetch(key, false, None, options).await? {
+                Fetched::Value { value, .. } => Ok(Some(value)),
+                Fetched::NotFound | Fetched::NotModified => Ok(None),
+            };
+        };
 
-        let resp = self
-            .client
-            .get(url)
-            .headers(traceparent_headers())
-            .send()
-            .await
-            .map_err(make_opaque_error)?;
+        let generation = near_cache.generation();
+        let lookup = if options.bypass_near_cache {
+            Lookup::Miss
+        } else {
+            near_cache.lookup(key)
+        };
+        let cached = match lookup {
+            Lookup::Fresh(value) => return Ok(Some(value)),
+            Lookup::Stale { value, etag } => Some((value, etag)),
+            Lookup::Miss => None,
+        };
 
-        match resp.status() {
-            StatusCode::NOT_FOUND => Ok(None),
-            StatusCode::OK => {
-                let body = resp.bytes().await.map_err(make_opaque_error)?;
-                Ok(Some(body.to_vec()))
+        let if_none_match = cached.as_ref().map(|(_, etag)| etag.as_str());
+        match self.fetch(key, true, if_none_match, options).await? {
+            Fetched::Value { value, etag } => {
+                near_cache.insert(key, value.clone(), etag, generation);
+                Ok(Some(value))
+            }
+            Fetched::NotFound => {
+                near_cache.invalidate(key);
+                Ok(None)
+            }
+            Fetched::NotModified => {
+                let (value, etag) = cached.expect("only revalidations are not modified");
+                near_cache.confirm(key, &etag);
+                Ok(Some(value))
             }
-            StatusCode::TOO_MANY_REQUESTS => Err(Error::TooManyRequests),
-            _ => Err(make_opaque_error(resp.status())),
         }
     }
 
-    /// Set the value associated with the given key.
-    pub async fn put(&self, key: &str, value: &[u8]) -> Result<(), Error> {
-        self.update_route_table_if_needed().await?;
+    /// Get the value associated with the given key as a stream of bytes.
+    ///
+    /// The value is not buffered, so that it can be piped to a file or a socket. Retries, hedging
+    /// and the deadline apply until the response starts; errors while reading the value are
+    /// returned by the stream. The near cache is not used.
+    ///
+    /// With the `metrics` feature, the request is recorded once the stream is dropped.
+    pub async fn get_stream(&self, key: &str) -> Result<Option<ValueStream>, Error> {
+        let request = self.make_request(Method::GET, None)?;
+        let options = RequestOptions::default();
+        self.call(key, request, true, &options, |url, resp| async move {
+            match resp.status() {
+                StatusCode::NOT_FOUND => Ok(None),
+                StatusCode::OK => Ok(Some(ValueStream::new(url, resp))),
+                _ => Err(Error::from_status(url, resp).await),
+            }
+        })
+        .await
+    }
 
-        let url = self.route(key).join(key).map_err(make_opaque_error)?;
+    /// Counters of the near cache, if it is enabled.
+    pub fn near_cache_stats(&self) -> Option<NearCacheStats> {
+        self.near_cache.as_ref().map(NearCache::stats)
+    }
 
-        let resp = self
-            .client
-            .put(url)
-            .headers(traceparent_headers())
-            .body(value.to_vec())
-            .send()
+    /// Set the value associated with the given key.
+    pub async fn put(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.put_with(key, value.to_vec(), &RequestOptions::default())
             .await
-            .map_err(make_opaque_error)?;
-
-        match resp.status() {
-            StatusCode::OK | StatusCode::CREATED => Ok(()),
-            StatusCode::TOO_MANY_REQUESTS => Err(Error::TooManyRequests),
-            status => Err(make_opaque_error(status)),
-        }
     }
 
     /// Set the value associated with the given key.
@@ -165,125 +390,524 @@ impl Client {
     /// * `bytes::Bytes`
     /// * `reqwest::Body`
     pub async fn put_owned<T: Into<Body>>(&self, key: &str, value: T) -> Result<(), Error> {
-        self.update_route_table_if_needed().await?;
+        self.put_with(key, value, &RequestOptions::default()).await
+    }
 
-        let url = self.route(key).join(key).map_err(make_opaque_error)?;
+    /// Set the value associated with the given key, with options for this call.
+    ///
+    /// Like [`Client::put_owned`], this method takes ownership of the value. A streaming
+    /// [`Body`] is never retried.
+    pub async fn put_with<T: Into<Body>>(
+        &self,
+        key: &str,
+        value: T,
+        options: &RequestOptions,
+    ) -> Result<(), Error> {
+        self.put_body(key, value.into(), HeaderMap::new(), options)
+            .await
+    }
 
-        let resp = self
-            .client
-            .put(url)
-            .headers(traceparent_headers())
-            .body(value)
-            .send()
+    /// Set the value associated with the given key to the chunks of a stream.
+    ///
+    /// The value is sent as it is produced, without being buffered. Pass `content_length` if the
+    /// size is known upfront; otherwise the value is sent with chunked transfer encoding. The
+    /// stream is consumed by the first attempt, so the call is never retried.
+    ///
+    /// For the same reason, a redirect to the owner of the key cannot be followed. The request is
+    /// sent to the owner in the route table; if that is stale and the node redirects, the table is
+    /// refreshed and the call fails with [`Error::Misrouted`].
+    pub async fn put_stream<S>(
+        &self,
+        key: &str,
+        stream: S,
+        content_length: Option<u64>,
+    ) -> Result<(), Error>
+    where
+        S: TryStream + Send + 'static,
+        S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        Bytes: From<S::Ok>,
+    {
+        self.put_stream_with(key, stream, content_length, &RequestOptions::default())
             .await
-            .map_err(make_opaque_error)?;
+    }
 
-        match resp.status() {
-            StatusCode::OK | StatusCode::CREATED => Ok(()),
-            StatusCode::TOO_MANY_REQUESTS => Err(Error::TooManyRequests),
-            status => Err(make_opaque_error(status)),
+    /// Set the value associated with the given key to the chunks of a stream, with options for
+    /// this call.
+    ///
+    /// See [`Client::put_stream`].
+    pub async fn put_stream_with<S>(
+        &self,
+        key: &str,
+        stream: S,
+        content_length: Option<u64>,
+        options: &RequestOptions,
+    ) -> Result<(), Error>
+    where
+        S: TryStream + Send + 'static,
+        S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        Bytes: From<S::Ok>,
+    {
+        let body = Body::wrap_stream(stream);
+        let mut headers = HeaderMap::new();
+        if let Some(content_length) = content_length {
+            headers.insert(CONTENT_LENGTH, content_length.into());
         }
+        self.put_body(key, body, headers, options).await
     }
 
-    /// Delete the value associated with the given key.
-    pub async fn delete(&self, key: &str) -> Result<(), Error> {
-        self.update_route_table_if_needed().await?;
+    /// Set the value associated with the given key to the content of a reader.
+    ///
+    /// Like [`Client::put_stream`], the value is not buffered, the call is never retried, and a
+    /// redirect fails with [`Error::Misrouted`].
+    pub async fn put_reader<R>(
+        &self,
+        key: &str,
+        reader: R,
+        content_length: Option<u64>,
+    ) -> Result<(), Error>
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        self.put_stream(key, ReaderStream::new(reader), content_length)
+            .await
+    }
 
-        let url = self.route(key).join(key).map_err(make_opaque_error)?;
+    async fn put_body(
+        &self,
+        key: &str,
+        body: Body,
+        headers: HeaderMap,
+        options: &RequestOptions,
+    ) -> Result<(), Error> {
+        let mut request = self.make_request(Method::PUT, Some(body))?;
+        request.headers_mut().extend(headers);
+        self.invalidate(key);
+        let result = self
+            .call(key, request, false, options, |url, resp| async move {
+                match resp.status() {
+                    StatusCode::OK | StatusCode::CREATED => Ok(()),
+                    // only returned when the body cannot be sent again to follow the redirect
+                    StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => {
+                        let location = resp
+                            .headers()
+                            .get(LOCATION)
+                            .and_then(|location| location.to_str().ok())
+                            .map(str::to_string);
+                        Err(Error::Misrouted { url, location })
+                    }
+                    _ => Err(Error::from_status(url, resp).await),
+                }
+            })
+            .await;
+        self.invalidate(key);
+        result
+    }
+
+    /// Get the value associated with the given key, decoded as written by [`Client::put_typed`].
+    ///
+    /// The value is decoded with the format and compression recorded in it, regardless of the
+    /// codec of this client.
+    pub async fn get_typed<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Error> {
+        let Some(value) = self.get(key).await? else {
+            return Ok(None);
+        };
+        Codec::decode(&value)
+            .map(Some)
+            .map_err(|source| Error::Deserialize {
+                key: key.to_string(),
+                source,
+            })
+    }
 
-        let resp = self
-            .client
-            .delete(url)
-            .headers(traceparent_headers())
-            .send()
+    /// Set the value associated with the given key to a value encoded with the codec of this
+    /// client, see [`ClientBuilder::codec`].
+    ///
+    /// The request names the codec in the `x-percas-codec` header, e.g. `json+zstd`.
+    pub async fn put_typed<T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let codec = self.codec;
+        let value = codec.encode(value).map_err(|source| Error::Serialize {
+            key: key.to_string(),
+            source,
+        })?;
+
+        let mut headers = HeaderMap::new();
+        let codec = HeaderValue::from_str(&codec.to_string()).expect("codec names are ascii");
+        headers.insert(CODEC_HEADER, codec);
+        self.put_body(key, value.into(), headers, &RequestOptions::default())
             .await
-            .map_err(make_opaque_error)?;
+    }
 
-        match resp.status() {
-            StatusCode::OK | StatusCode::NO_CONTENT => Ok(()),
-            StatusCode::TOO_MANY_REQUESTS => Err(Error::TooManyRequests),
-            status => Err(make_opaque_error(status)),
-        }
+    /// Delete the value associated with the given key.
+    pub async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.delete_with(key, &RequestOptions::default()).await
+    }
+
+    /// Delete the value associated with the given key, with options for this call.
+    pub async fn delete_with(&self, key: &str, options: &RequestOptions) -> Result<(), Error> {
+        let request = self.make_request(Method::DELETE, None)?;
+        self.invalidate(key);
+        let result = self
+            .call(key, request, true, options, |url, resp| async move {
+                match resp.status() {
+                    StatusCode::OK | StatusCode::NO_CONTENT => Ok(()),
+                    _ => Err(Error::from_status(url, resp).await),
+                }
+            })
+            .await;
+        self.invalidate(key);
+        result
     }
 
     /// Get the version of the Percas server.
     pub async fn version(&self) -> Result<Version, Error> {
-        let url = self.ctrl_url.join("version").map_err(make_opaque_error)?;
-
-        let resp = self
-            .client
-            .get(url)
-            .headers(traceparent_headers())
-            .send()
-            .await
-            .map_err(make_opaque_error)?;
+        let url = self.ctrl_url("version")?;
 
-        match resp.status() {
-            StatusCode::OK => resp.json::<Version>().await.map_err(make_opaque_error),
-            StatusCode::TOO_MANY_REQUESTS => Err(Error::TooManyRequests),
-            status => Err(make_opaque_error(status)),
+        async {
+            let resp = self
+                .client
+                .get(url.clone())
+                .headers(traceparent_headers())
+                .send()
+                .await
+                .map_err(|err| Error::from_reqwest(url.clone(), err))?;
+
+            match resp.status() {
+                StatusCode::OK => resp
+                    .json::<Version>()
+                    .await
+                    .map_err(|err| Error::from_reqwest(url, err)),
+                _ => Err(Error::from_status(url, resp).await),
+            }
         }
+        .in_span(Span::enter_with_local_parent("client.version"))
+        .await
     }
 }
 
 impl Client {
-    fn route(&self, key: &str) -> Url {
-        if let Some(route_table) = &*self.route_table.read().unwrap()
-            && let Some((_, url)) = route_table.lookup(key)
-        {
-            url.clone()
-        } else {
-            self.data_url.clone()
+    /// Read a key from the server, conditionally if the entity tag of a cached value is given.
+    ///
+    /// With `want_etag`, the server returns the entity tag of the value, if any.
+    async fn fetch(
+        &self,
+        key: &str,
+        want_etag: bool,
+        if_none_match: Option<&str>,
+        options: &RequestOptions,
+    ) -> Result<Fetched, Error> {
+        let mut request = self.make_request(Method::GET, None)?;
+        if want_etag {
+            request
+                .headers_mut()
+                .insert(ETAG_HEADER, HeaderValue::from_static("1"));
         }
+        if let Some(etag) = if_none_match {
+            let etag = etag
+                .parse()
+                .expect("entity tags are read from valid header values");
+            request.headers_mut().insert(IF_NONE_MATCH, etag);
+        }
+
+        self.call(key, request, true, options, |url, resp| async move {
+            match resp.status() {
+                StatusCode::NOT_FOUND => Ok(Fetched::NotFound),
+                StatusCode::NOT_MODIFIED => Ok(Fetched::NotModified),
+                StatusCode::OK => {
+                    let etag = resp
+                        .headers()
+                        .get(ETAG)
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string);
+                    let record = resp.extensions().get::<metrics::RequestRecord>().cloned();
+                    let value = resp
+                        .bytes()
+                        .await
+                        .map_err(|err| Error::from_reqwest(url, err))?;
+                    if let Some(record) = record {
+                        record.add_received(value.len() as u64);
+                    }
+                    Ok(Fetched::Value {
+                        value: value.to_vec(),
+                        etag,
+                    })
+                }
+                _ => Err(Error::from_status(url, resp).await),
+            }
+        })
+        .await
     }
 
-    async fn update_route_table_if_needed(&self) -> Result<(), Error> {
-        let url = self.ctrl_url.join("members").map_err(make_opaque_error)?;
+    /// Drop the cached value of a key this client writes or deletes.
+    ///
+    /// It is called both before and after the request, so that a concurrent read cannot cache
+    /// the value being replaced.
+    fn invalidate(&self, key: &str) {
+        if let Some(near_cache) = &self.near_cache {
+            near_cache.invalidate(key);
+        }
+    }
 
-        if self.last_updated.read().unwrap().elapsed() > UPDATE_ROUTE_TABLE_INTERVAL {
-            #[derive(Deserialize)]
-            #[expect(dead_code)] // some fields may be unused
-            struct Member {
-                node_id: Uuid,
-                advertise_data_url: Url,
-                advertise_ctrl_url: Url,
-                incarnation: u64,
-                vnodes: Vec<u32>,
-            }
+    /// Make a data request; its url is set to the owner of the key on each attempt.
+    fn make_request(&self, method: Method, body: Option<Body>) -> Result<reqwest::Request, Error> {
+        let mut request = self.client.request(method, self.data_url.clone());
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+        request
+            .build()
+            .map_err(|err| Error::from_reqwest(self.data_url.clone(), err))
+    }
+
+    /// Run a data request under the deadline, retry and hedge policies.
+    async fn call<T, F, Fut>(
+        &self,
+        key: &str,
+        request: reqwest::Request,
+        idempotent: bool,
+        options: &RequestOptions,
+        handle: F,
+    ) -> Result<T, Error>
+    where
+        F: Fn(Url, reqwest::Response) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let name = match *request.method() {
+            Method::GET => "client.get",
+            Method::PUT => "client.put",
+            Method::DELETE => "client.delete",
+            _ => "client.request",
+        };
+        let span = Span::enter_with_local_parent(name)
+            .with_property(|| ("key_size", key.len().to_string()));
+        let attempts = self
+            .attempts(key, request, idempotent, &handle)
+            .in_span(span);
+        match options.timeout.or(self.timeout) {
+            None => attempts.await,
+            Some(timeout) => match tokio::time::timeout(timeout, attempts).await {
+                Ok(result) => result,
+                Err(_) => Err(Error::DeadlineExceeded {
+                    url: self.key_url(key)?,
+                    timeout,
+                }),
+            },
+        }
+    }
+
+    async fn attempts<T, F, Fut>(
+        &self,
+        key: &str,
+        request: reqwest::Request,
+        idempotent: bool,
+        handle: &F,
+    ) -> Result<T, Error>
+    where
+        F: Fn(Url, reqwest::Response) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut backoff = self.retry.backoff();
+        let mut pending = Some(request);
+        loop {
+            self.ensure_route_table().await;
+            let url = self.key_url(key)?;
+
+            // keep the request for another attempt unless its body cannot be replayed
+            let request = pending.take().expect("request is kept for each attempt");
+            let mut attempt = match request.try_clone() {
+                Some(attempt) => {
+                    pending = Some(request);
+                    attempt
+                }
+                None => request,
+            };
+            *attempt.url_mut() = url.clone();
+
+            let result = match &self.latency {
+                Some(latency) if attempt.method() == Method::GET => {
+                    self.send_hedged(url, attempt, handle, latency).await
+                }
+                _ => self.send(url, attempt, handle).await,
+            };
+            let err = match result {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
 
-            #[derive(Deserialize)]
-            struct ListMembersResponse {
-                members: Vec<Member>,
+            if matches!(err, Error::Connect { .. } | Error::Misrouted { .. }) {
+                // the owner may have left the cluster, or moved; keep the current table if the
+                // refresh fails too, so that the retry below still has a chance
+                let _ = self.router.refresh_if_due().await;
             }
 
-            let resp = self
-                .client
-                .get(url)
-                .headers(traceparent_headers())
-                .send()
-                .await
-                .map_err(make_opaque_error)?;
+            if pending.is_none() || !self.retry.should_retry(&err, idempotent) {
+                return Err(err);
+            }
+            match backoff.next() {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return Err(err),
+            }
+        }
+    }
 
-            let members = match resp.status() {
-                StatusCode::OK => {
-                    resp.json::<ListMembersResponse>()
-                        .await
-                        .map_err(make_opaque_error)?
-                        .members
+    async fn send<T, F, Fut>(
+        &self,
+        url: Url,
+        request: reqwest::Request,
+        handle: &F,
+    ) -> Result<T, Error>
+    where
+        F: Fn(Url, reqwest::Response) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let method = request.method().clone();
+        // a streaming body is counted by its declared length, if any
+        let sent = request
+            .body()
+            .and_then(Body::as_bytes)
+            .map(|body| body.len() as u64)
+            .or_else(|| {
+                let len = request.headers().get(CONTENT_LENGTH)?;
+                len.to_str().ok()?.parse().ok()
+            })
+            .unwrap_or(0);
+        let span = Span::enter_with_local_parent("client.attempt")
+            .with_property(|| ("node", url.to_string()));
+
+        async move {
+            let start = Instant::now();
+            let mut request = request;
+            request.headers_mut().extend(traceparent_headers());
+            let mut resp = match self.client.execute(request).await {
+                Ok(resp) => resp,
+                Err(err) => {
+                    let err = Error::from_reqwest(url.clone(), err);
+                    metrics::record_request(&method, &url, Err(&err), start.elapsed(), sent);
+                    return Err(err);
                 }
-                StatusCode::TOO_MANY_REQUESTS => return Err(Error::TooManyRequests),
-                status => return Err(make_opaque_error(status)),
             };
 
-            let mut route_table = RouteTable::default();
-            for member in members {
-                for vnode in member.vnodes {
-                    route_table.insert(vnode, member.node_id, member.advertise_data_url.clone());
+            let status = resp.status();
+            LocalSpan::add_property(|| ("http.status_code", status.as_str().to_string()));
+            // recorded when both the handler and any stream of the value it returns are done
+            let record = metrics::RequestRecord::new(method, url.clone(), status, start, sent);
+            resp.extensions_mut().insert(record.clone());
+            self.router.observe_response(&url, &resp);
+            handle(url, resp).await
+        }
+        .in_span(span)
+        .await
+    }
+
+    /// Send a read, and the same read again if it is slower than usual.
+    ///
+    /// The hedged read goes to the same owner, as values are not replicated. The pending read
+    /// holds its HTTP/1.1 connection, so the hedged one is sent on another connection, which
+    /// avoids a stalled connection or a request stuck behind a slow one on the node.
+    async fn send_hedged<T, F, Fut>(
+        &self,
+        url: Url,
+        request: reqwest::Request,
+        handle: &F,
+        latency: &LatencyTracker,
+    ) -> Result<T, Error>
+    where
+        F: Fn(Url, reqwest::Response) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let start = Instant::now();
+        let hedge = latency.delay().zip(request.try_clone());
+        let result = match hedge {
+            None => self.send(url, request, handle).await,
+            Some((delay, hedge_request)) => {
+                let hedge_url = url.clone();
+                let primary = pin!(self.send(url, request, handle));
+                let hedged = pin!(async {
+                    tokio::time::sleep(delay).await;
+                    self.send(hedge_url, hedge_request, handle).await
+                });
+                match select(primary, hedged).await {
+                    Either::Left((result, _)) => result,
+                    Either::Right((Ok(value), _)) => Ok(value),
+                    Either::Right((Err(_), primary)) => primary.await,
                 }
             }
-            *self.route_table.write().unwrap() = Some(route_table);
-            *self.last_updated.write().unwrap() = Instant::now();
+        };
+        if result.is_ok() {
+            latency.record(start.elapsed());
+        }
+        result
+    }
+
+    fn key_url(&self, key: &str) -> Result<Url, Error> {
+        let base = self.route(key);
+        base.join(key).map_err(|err| Error::invalid_url(base, err))
+    }
+
+    fn ctrl_url(&self, path: &str) -> Result<Url, Error> {
+        let ctrl_url = &self.router.ctrl_urls()[0];
+        ctrl_url
+            .join(path)
+            .map_err(|err| Error::invalid_url(ctrl_url, err))
+    }
+
+    /// Start refreshing the route table in the background, and fetch the first table inline.
+    ///
+    /// Until a control server answers, requests go to the data server url, which redirects them.
+    /// The refresher runs on the runtime of the call that started it, and is started again on
+    /// the current runtime if that one has shut down.
+    async fn ensure_route_table(&self) {
+        {
+            let mut refresher = self.refresher.lock().unwrap_or_else(|e| e.into_inner());
+            if refresher.as_ref().is_none_or(JoinHandle::is_finished) {
+                *refresher = Some(tokio::spawn(self.router.clone().run()));
+            }
+        }
+        if self.router.table().is_none() {
+            let _ = self.router.refresh_if_due().await;
         }
-        Ok(())
+    }
+
+    fn route(&self, key: &str) -> Url {
+        if let Some(route_table) = self.router.table()
+            && let Some((_, url)) = route_table.lookup(key)
+        {
+            url.clone()
+        } else {
+            self.data_url.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_respawn_refresher() {
+        let runtime = || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+        };
+        let is_running = |client: &Client| {
+            let refresher = client.refresher.lock().unwrap();
+            refresher.as_ref().is_some_and(|task| !task.is_finished())
+        };
+        let client = ClientBuilder::new("http://127.0.0.1:1", "http://127.0.0.1:1")
+            .build()
+            .unwrap();
+
+        // the refresher ends with the runtime it was spawned on
+        runtime().block_on(client.ensure_route_table());
+        assert!(!is_running(&client));
+
+        let rt = runtime();
+        rt.block_on(client.ensure_route_table());
+        assert!(is_running(&client));
     }
 }
@@ -0,0 +1,344 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Serialization and compression of typed values.
+//!
+//! A typed value starts with a 4 bytes header, so that any client, or a tool inspecting the
+//! stored bytes, can tell how it is encoded:
+//!
+//! | offset | size | content                                              |
+//! |--------|------|------------------------------------------------------|
+//! | 0      | 2    | the magic `PC`                                       |
+//! | 2      | 1    | the format: `1` JSON, `2` bincode, `3` postcard      |
+//! | 3      | 1    | the compression: `0` none, `1` zstd, `2` lz4        |
+//!
+//! The header is followed by the serialized, then possibly compressed, value. The request storing
+//! the value names the codec in the `x-percas-codec` header, e.g. `json` or `postcard+zstd`, which
+//! the server records in its access log.
+
+use std::fmt;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const MAGIC: [u8; 2] = *b"PC";
+const HEADER_LEN: usize = 4;
+
+/// The request header naming the codec of a typed value.
+pub(crate) const CODEC_HEADER: &str = "x-percas-codec";
+
+/// The serialization format of a typed value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Format {
+    /// JSON, readable by any client.
+    #[default]
+    Json,
+    /// bincode with its standard configuration.
+    #[cfg(feature = "bincode")]
+    Bincode,
+    /// postcard.
+    #[cfg(feature = "postcard")]
+    Postcard,
+}
+
+impl Format {
+    /// The name of this format, as in the `x-percas-codec` header.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            #[cfg(feature = "bincode")]
+            Format::Bincode => "bincode",
+            #[cfg(feature = "postcard")]
+            Format::Postcard => "postcard",
+        }
+    }
+
+    fn tag(self) -> u8 {
+        match self {
+            Format::Json => 1,
+            #[cfg(feature = "bincode")]
+            Format::Bincode => 2,
+            #[cfg(feature = "postcard")]
+            Format::Postcard => 3,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Result<Self, BoxError> {
+        match tag {
+            1 => Ok(Format::Json),
+            #[cfg(feature = "bincode")]
+            2 => Ok(Format::Bincode),
+            #[cfg(feature = "postcard")]
+            3 => Ok(Format::Postcard),
+            _ => Err(InvalidHeader(format!("unsupported format {tag}")).into()),
+        }
+    }
+
+    fn serialize<T: Serialize + ?Sized>(self, value: &T) -> Result<Vec<u8>, BoxError> {
+        let mut buf = MAGIC.to_vec();
+        buf.extend([self.tag(), Compression::None.tag()]);
+        match self {
+            Format::Json => serde_json::to_writer(&mut buf, value)?,
+            #[cfg(feature = "bincode")]
+            Format::Bincode => {
+                bincode::serde::encode_into_std_write(
+                    value,
+                    &mut buf,
+                    bincode::config::standard(),
+                )?;
+            }
+            #[cfg(feature = "postcard")]
+            Format::Postcard => buf = postcard::to_extend(value, buf)?,
+        }
+        Ok(buf)
+    }
+
+    fn deserialize<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, BoxError> {
+        match self {
+            Format::Json => Ok(serde_json::from_slice(bytes)?),
+            #[cfg(feature = "bincode")]
+            Format::Bincode => {
+                let (value, _) =
+                    bincode::serde::decode_from_slice(bytes, bincode::config::standard())?;
+                Ok(value)
+            }
+            #[cfg(feature = "postcard")]
+            Format::Postcard => Ok(postcard::from_bytes(bytes)?),
+        }
+    }
+}
+
+/// The compression of a typed value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// The value is stored as serialized.
+    #[default]
+    None,
+    /// zstd with its default level.
+    #[cfg(feature = "zstd")]
+    Zstd,
+    /// lz4, in the frame-less block format prefixed with the uncompressed size.
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+impl Compression {
+    /// The name of this compression, as in the `x-percas-codec` header, or `None` if values are
+    /// not compressed.
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Some("zstd"),
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => Some("lz4"),
+        }
+    }
+
+    fn tag(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => 1,
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => 2,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Result<Self, BoxError> {
+        match tag {
+            0 => Ok(Compression::None),
+            #[cfg(feature = "zstd")]
+            1 => Ok(Compression::Zstd),
+            #[cfg(feature = "lz4")]
+            2 => Ok(Compression::Lz4),
+            _ => Err(InvalidHeader(format!("unsupported compression {tag}")).into()),
+        }
+    }
+
+    fn compress(self, buf: Vec<u8>) -> Result<Vec<u8>, BoxError> {
+        let compressed: Option<Vec<u8>> = match self {
+            Compression::None => None,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Some(zstd::stream::encode_all(&buf[HEADER_LEN..], 0)?),
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => Some(lz4_flex::compress_prepend_size(&buf[HEADER_LEN..])),
+        };
+        let Some(compressed) = compressed else {
+            return Ok(buf);
+        };
+
+        let mut out = Vec::with_capacity(HEADER_LEN + compressed.len());
+        out.extend_from_slice(&buf[..HEADER_LEN - 1]);
+        out.push(self.tag());
+        out.extend(compressed);
+        Ok(out)
+    }
+
+    fn decompress(self, data: &[u8]) -> Result<std::borrow::Cow<'_, [u8]>, BoxError> {
+        match self {
+            Compression::None => Ok(data.into()),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(zstd::stream::decode_all(data)?.into()),
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => Ok(lz4_flex::decompress_size_prepended(data)?.into()),
+        }
+    }
+}
+
+/// How a [`Client`](crate::Client) encodes the values of
+/// [`Client::put_typed`](crate::Client::put_typed).
+///
+/// The encoding is recorded in each value, so [`Client::get_typed`](crate::Client::get_typed)
+/// decodes values written with any codec, as long as the client has the features of its format
+/// and compression enabled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Codec {
+    format: Format,
+    compression: Compression,
+}
+
+impl Codec {
+    /// Serialize values with the given format, uncompressed.
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            compression: Compression::None,
+        }
+    }
+
+    /// Set how serialized values are compressed.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// The serialization format.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// The compression.
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub(crate) fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, BoxError> {
+        let buf = self.format.serialize(value)?;
+        self.compression.compress(buf)
+    }
+
+    pub(crate) fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, BoxError> {
+        if bytes.len() < HEADER_LEN || bytes[..2] != MAGIC {
+            return Err(InvalidHeader("value is not written by put_typed".to_string()).into());
+        }
+        let format = Format::from_tag(bytes[2])?;
+        let compression = Compression::from_tag(bytes[3])?;
+        let data = compression.decompress(&bytes[HEADER_LEN..])?;
+        format.deserialize(&data)
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.format.name())?;
+        if let Some(compression) = self.compression.name() {
+            write!(f, "+{compression}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct InvalidHeader(String);
+
+impl fmt::Display for InvalidHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidHeader {}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Value {
+        id: u64,
+        name: String,
+        tags: Vec<String>,
+    }
+
+    fn value() -> Value {
+        Value {
+            id: 42,
+            name: "percas".to_string(),
+            tags: vec!["cache".to_string(); 16],
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let codecs = [
+            Codec::default(),
+            #[cfg(feature = "bincode")]
+            Codec::new(Format::Bincode),
+            #[cfg(feature = "postcard")]
+            Codec::new(Format::Postcard),
+            #[cfg(feature = "zstd")]
+            Codec::default().with_compression(Compression::Zstd),
+            #[cfg(feature = "lz4")]
+            Codec::default().with_compression(Compression::Lz4),
+        ];
+
+        for codec in codecs {
+            let bytes = codec.encode(&value()).unwrap();
+            assert_eq!(&bytes[..2], b"PC");
+            assert_eq!(bytes[2], codec.format().tag());
+            assert_eq!(bytes[3], codec.compression().tag());
+            assert_eq!(
+                Codec::decode::<Value>(&bytes).unwrap(),
+                value(),
+                "{codec:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_name() {
+        assert_eq!(Codec::default().to_string(), "json");
+        #[cfg(feature = "zstd")]
+        assert_eq!(
+            Codec::default()
+                .with_compression(Compression::Zstd)
+                .to_string(),
+            "json+zstd"
+        );
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        assert!(Codec::decode::<Value>(b"{}").is_err());
+        assert!(Codec::decode::<Value>(b"PC\x09\x00{}").is_err());
+        assert!(Codec::decode::<Value>(b"PC\x01\x09{}").is_err());
+    }
+}
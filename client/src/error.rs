@@ -0,0 +1,286 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use reqwest::Url;
+
+/// Errors returned by the Percas client.
+///
+/// Every variant that involves a request carries the URL of the node it was sent to. Use
+/// [`Error::is_retryable`] to decide whether the same call may succeed when tried again.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A server URL, or a URL derived from it, is malformed.
+    InvalidUrl {
+        /// The URL, or the base of the URL, that failed to parse.
+        url: String,
+        /// The parse error.
+        source: url::ParseError,
+    },
+    /// The underlying HTTP client could not be built.
+    Build {
+        /// The error from the HTTP client.
+        source: reqwest::Error,
+    },
+    /// The runtime of the blocking client could not be started.
+    #[cfg(feature = "blocking")]
+    Runtime {
+        /// The error from starting the runtime.
+        source: std::io::Error,
+    },
+    /// The node could not be connected.
+    Connect {
+        /// The URL of the request.
+        url: Url,
+        /// The error from the HTTP client.
+        source: reqwest::Error,
+    },
+    /// The request or its response timed out.
+    Timeout {
+        /// The URL of the request.
+        url: Url,
+        /// The error from the HTTP client.
+        source: reqwest::Error,
+    },
+    /// The call, including all its retries, did not complete before its deadline.
+    DeadlineExceeded {
+        /// The URL of the node the key is routed to.
+        url: Url,
+        /// The deadline of the call.
+        timeout: Duration,
+    },
+    /// The request failed in transit after the node was connected.
+    Transport {
+        /// The URL of the request.
+        url: Url,
+        /// The error from the HTTP client.
+        source: reqwest::Error,
+    },
+    /// The node responded with an unexpected HTTP status.
+    Status {
+        /// The URL of the request.
+        url: Url,
+        /// The status of the response.
+        status: StatusCode,
+        /// The body of the response, usually a short description of the status.
+        body: String,
+    },
+    /// The request was redirected too many times, usually while nodes disagree on the ring.
+    RedirectLoop {
+        /// The URL of the request.
+        url: Url,
+        /// The error from the HTTP client.
+        source: reqwest::Error,
+    },
+    /// The node redirected a request to the owner of the key, but its streaming body cannot be
+    /// sent again.
+    ///
+    /// The route table of the client was stale and is refreshed, so calling again with a new
+    /// stream usually reaches the owner.
+    Misrouted {
+        /// The URL of the request.
+        url: Url,
+        /// The `Location` the node redirected the request to, if it said so.
+        location: Option<String>,
+    },
+    /// The route table could not be refreshed from the control server.
+    RouteRefresh {
+        /// The URL of the control server endpoint.
+        url: Url,
+        /// The error of the refresh request.
+        source: Box<Error>,
+    },
+    /// The response body could not be decoded.
+    Decode {
+        /// The URL of the request.
+        url: Url,
+        /// The error from the HTTP client.
+        source: reqwest::Error,
+    },
+    /// A typed value could not be serialized or compressed.
+    Serialize {
+        /// The key of the value.
+        key: String,
+        /// The error from the codec.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// A stored value could not be decompressed or deserialized as the requested type.
+    Deserialize {
+        /// The key of the value.
+        key: String,
+        /// The error from the codec.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+impl Error {
+    /// Whether the failed call may succeed if it is tried again.
+    ///
+    /// Connection failures, timeouts, exceeded deadlines, redirect loops, misrouted streams and the
+    /// statuses `408`, `429`, `500`, `502`, `503` and `504` are retryable. Malformed URLs, decode
+    /// and codec errors and other statuses are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::InvalidUrl { .. }
+            | Error::Build { .. }
+            | Error::Decode { .. }
+            | Error::Serialize { .. }
+            | Error::Deserialize { .. } => false,
+            #[cfg(feature = "blocking")]
+            Error::Runtime { .. } => false,
+            Error::Connect { .. }
+            | Error::Timeout { .. }
+            | Error::DeadlineExceeded { .. }
+            | Error::Transport { .. }
+            | Error::RedirectLoop { .. }
+            | Error::Misrouted { .. } => true,
+            Error::Status { status, .. } => matches!(
+                *status,
+                StatusCode::REQUEST_TIMEOUT
+                    | StatusCode::TOO_MANY_REQUESTS
+                    | StatusCode::INTERNAL_SERVER_ERROR
+                    | StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            ),
+            Error::RouteRefresh { source, .. } => source.is_retryable(),
+        }
+    }
+
+    /// The URL of the node involved, if any.
+    pub fn url(&self) -> Option<&Url> {
+        match self {
+            Error::InvalidUrl { .. }
+            | Error::Build { .. }
+            | Error::Serialize { .. }
+            | Error::Deserialize { .. } => None,
+            #[cfg(feature = "blocking")]
+            Error::Runtime { .. } => None,
+            Error::Connect { url, .. }
+            | Error::Timeout { url, .. }
+            | Error::DeadlineExceeded { url, .. }
+            | Error::Transport { url, .. }
+            | Error::Status { url, .. }
+            | Error::RedirectLoop { url, .. }
+            | Error::Misrouted { url, .. }
+            | Error::RouteRefresh { url, .. }
+            | Error::Decode { url, .. } => Some(url),
+        }
+    }
+
+    /// The HTTP status the node responded with, if the error is caused by one.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Status { status, .. } => Some(*status),
+            Error::RouteRefresh { source, .. } => source.status(),
+            _ => None,
+        }
+    }
+
+    /// Classify an error from the HTTP client for a request to `url`.
+    pub(crate) fn from_reqwest(url: Url, source: reqwest::Error) -> Self {
+        if source.is_timeout() {
+            Error::Timeout { url, source }
+        } else if source.is_connect() {
+            Error::Connect { url, source }
+        } else if source.is_redirect() {
+            Error::RedirectLoop { url, source }
+        } else if source.is_decode() {
+            Error::Decode { url, source }
+        } else {
+            Error::Transport { url, source }
+        }
+    }
+
+    /// Turn a response with an unexpected status into an error, keeping its body for context.
+    pub(crate) async fn from_status(url: Url, resp: reqwest::Response) -> Self {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        Error::Status { url, status, body }
+    }
+
+    pub(crate) fn invalid_url(url: impl ToString, source: url::ParseError) -> Self {
+        Error::InvalidUrl {
+            url: url.to_string(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidUrl { url, .. } => write!(f, "invalid url {url}"),
+            Error::Build { .. } => write!(f, "failed to build http client"),
+            #[cfg(feature = "blocking")]
+            Error::Runtime { .. } => write!(f, "failed to start the blocking client runtime"),
+            Error::Connect { url, .. } => write!(f, "failed to connect to {url}"),
+            Error::Timeout { url, .. } => write!(f, "request to {url} timed out"),
+            Error::DeadlineExceeded { url, timeout } => {
+                write!(f, "request to {url} did not complete in {timeout:?}")
+            }
+            Error::Transport { url, .. } => write!(f, "request to {url} failed"),
+            Error::Status { url, status, body } if body.is_empty() => {
+                write!(f, "{url} responded with {status}")
+            }
+            Error::Status { url, status, body } => {
+                write!(f, "{url} responded with {status}: {body}")
+            }
+            Error::RedirectLoop { url, .. } => write!(f, "too many redirects for {url}"),
+            Error::Misrouted {
+                url,
+                location: Some(location),
+            } => write!(f, "{url} redirected a streaming request to {location}"),
+            Error::Misrouted {
+                url,
+                location: None,
+            } => {
+                write!(f, "{url} redirected a streaming request")
+            }
+            Error::RouteRefresh { url, .. } => {
+                write!(f, "failed to refresh route table from {url}")
+            }
+            Error::Decode { url, .. } => write!(f, "failed to decode response from {url}"),
+            Error::Serialize { key, .. } => write!(f, "failed to serialize the value of {key}"),
+            Error::Deserialize { key, .. } => {
+                write!(f, "failed to deserialize the value of {key}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidUrl { source, .. } => Some(source),
+            #[cfg(feature = "blocking")]
+            Error::Runtime { source } => Some(source),
+            Error::Build { source }
+            | Error::Connect { source, .. }
+            | Error::Timeout { source, .. }
+            | Error::Transport { source, .. }
+            | Error::RedirectLoop { source, .. }
+            | Error::Decode { source, .. } => Some(source),
+            Error::RouteRefresh { source, .. } => Some(source.as_ref()),
+            Error::Serialize { source, .. } | Error::Deserialize { source, .. } => {
+                Some(source.as_ref())
+            }
+            Error::DeadlineExceeded { .. } | Error::Status { .. } | Error::Misrouted { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+// number of recent latencies the hedge delay is computed from
+const WINDOW: usize = 256;
+// no hedging until this many latencies are observed
+const MIN_SAMPLES: usize = 32;
+// recompute the hedge delay every this many latencies
+const RECOMPUTE_EVERY: usize = 16;
+
+/// When a [`Client`](crate::Client) sends a hedged read.
+///
+/// If a `get` has not completed after the given percentile of recent `get` latencies, the same
+/// request is sent again, and whichever succeeds first is returned.
+///
+/// Values are not replicated, so the hedged request goes to the same owner, on another
+/// connection. Hedging thus cuts the tail caused by a stalled connection or a request that is
+/// slow on its own, but not the tail of a node that is slow as a whole.
+#[derive(Debug, Clone)]
+pub struct HedgePolicy {
+    percentile: f64,
+    min_delay: Duration,
+}
+
+impl HedgePolicy {
+    /// Hedge reads slower than the given percentile of recent reads, e.g. `0.95`.
+    ///
+    /// The percentile is clamped to `0.0..=1.0`.
+    pub fn new(percentile: f64) -> Self {
+        Self {
+            percentile: percentile.clamp(0.0, 1.0),
+            min_delay: Duration::from_millis(1),
+        }
+    }
+
+    /// Set the minimal delay before a hedged read is sent.
+    pub fn with_min_delay(mut self, min_delay: Duration) -> Self {
+        self.min_delay = min_delay;
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    latencies: Vec<Duration>,
+    recorded: usize,
+}
+
+/// Tracks recent read latencies and derives the hedge delay from them.
+#[derive(Debug)]
+pub(crate) struct LatencyTracker {
+    policy: HedgePolicy,
+    samples: Mutex<Samples>,
+    // in microseconds; zero until enough samples are observed
+    delay: AtomicU64,
+}
+
+impl LatencyTracker {
+    pub(crate) fn new(policy: HedgePolicy) -> Self {
+        Self {
+            policy,
+            samples: Mutex::new(Samples::default()),
+            delay: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn record(&self, latency: Duration) {
+        let mut samples = self.samples.lock().unwrap();
+        let index = samples.recorded % WINDOW;
+        if index < samples.latencies.len() {
+            samples.latencies[index] = latency;
+        } else {
+            samples.latencies.push(latency);
+        }
+        samples.recorded += 1;
+
+        if samples.recorded >= MIN_SAMPLES && samples.recorded.is_multiple_of(RECOMPUTE_EVERY) {
+            let mut sorted = samples.latencies.clone();
+            drop(samples);
+            sorted.sort_unstable();
+            let rank = ((sorted.len() - 1) as f64 * self.policy.percentile).round() as usize;
+            let delay = sorted[rank].max(self.policy.min_delay);
+            let micros = u64::try_from(delay.as_micros()).unwrap_or(u64::MAX).max(1);
+            self.delay.store(micros, Ordering::Relaxed);
+        }
+    }
+
+    /// The delay after which a read is hedged, or `None` if too few reads are observed.
+    pub(crate) fn delay(&self) -> Option<Duration> {
+        match self.delay.load(Ordering::Relaxed) {
+            0 => None,
+            micros => Some(Duration::from_micros(micros)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hedge_delay() {
+        let tracker = LatencyTracker::new(HedgePolicy::new(0.9));
+        for i in 1..MIN_SAMPLES as u64 {
+            tracker.record(Duration::from_millis(i));
+        }
+        assert_eq!(tracker.delay(), None);
+
+        // 1ms..=32ms, p90 is rank 28
+        tracker.record(Duration::from_millis(MIN_SAMPLES as u64));
+        assert_eq!(tracker.delay(), Some(Duration::from_millis(29)));
+
+        // the window slides over old samples
+        for _ in 0..WINDOW {
+            tracker.record(Duration::from_micros(10));
+        }
+        assert_eq!(tracker.delay(), Some(Duration::from_millis(1)));
+    }
+}
@@ -16,30 +16,34 @@
 
 #![deny(missing_docs)]
 
+mod cache;
 mod client;
+mod codec;
+mod error;
+mod hedge;
+mod metrics;
+mod retry;
 mod route;
+mod router;
+mod stream;
 
+pub use self::cache::NearCachePolicy;
+pub use self::cache::NearCacheStats;
 pub use self::client::Client;
 pub use self::client::ClientBuilder;
+pub use self::client::RequestOptions;
+pub use self::codec::Codec;
+pub use self::codec::Compression;
+pub use self::codec::Format;
+pub use self::error::Error;
+pub use self::hedge::HedgePolicy;
+pub use self::retry::RetryPolicy;
+pub use self::stream::ValueStream;
 
 pub mod protos;
 
-/// Errors that can occur when using the client.
-#[derive(Debug)]
-pub enum Error {
-    /// The server responded with a "429 Too Many Requests" status code.
-    TooManyRequests,
-    /// An opaque error message from the server.
-    Opaque(String),
-}
+#[cfg(feature = "blocking")]
+pub mod blocking;
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Error::TooManyRequests => write!(f, "Too many requests"),
-            Error::Opaque(msg) => write!(f, "{msg}"),
-        }
-    }
-}
-
-impl std::error::Error for Error {}
+#[cfg(feature = "testing")]
+pub mod testing;
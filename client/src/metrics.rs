@@ -0,0 +1,234 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Metrics of the client, recorded with the global OpenTelemetry meter provider if the
+//! `metrics` feature is enabled.
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) use self::noop::*;
+#[cfg(feature = "metrics")]
+pub(crate) use self::otel::*;
+
+#[cfg(feature = "metrics")]
+mod otel {
+    use std::sync::Arc;
+    use std::sync::LazyLock;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use opentelemetry::KeyValue;
+    use opentelemetry::metrics::Counter;
+    use opentelemetry::metrics::Histogram;
+    use reqwest::Method;
+    use reqwest::StatusCode;
+    use reqwest::Url;
+
+    use crate::Error;
+
+    struct ClientMetrics {
+        count: Counter<u64>,
+        bytes: Counter<u64>,
+        duration: Histogram<f64>,
+        redirects: Counter<u64>,
+        route_refreshes: Counter<u64>,
+    }
+
+    impl ClientMetrics {
+        fn new() -> Self {
+            let meter = opentelemetry::global::meter("percas-client");
+            Self {
+                count: meter
+                    .u64_counter("percas.client.request.count")
+                    .with_description("The number of requests sent, by node and status")
+                    .build(),
+                bytes: meter
+                    .u64_counter("percas.client.request.bytes")
+                    .with_description("The number of value bytes sent and received")
+                    .with_unit("byte")
+                    .build(),
+                duration: meter
+                    .f64_histogram("percas.client.request.duration")
+                    .with_description("The duration of a request, until its value is read")
+                    .with_unit("second")
+                    .with_boundaries(
+                        [
+                            0.0001, 0.0005, 0.001, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.5, 1.0, 5.0,
+                        ]
+                        .into(),
+                    )
+                    .build(),
+                redirects: meter
+                    .u64_counter("percas.client.redirects")
+                    .with_description("The number of requests redirected away from a node")
+                    .build(),
+                route_refreshes: meter
+                    .u64_counter("percas.client.route_refreshes")
+                    .with_description("The number of route table refreshes")
+                    .build(),
+            }
+        }
+
+        fn get() -> &'static ClientMetrics {
+            static METRICS: LazyLock<ClientMetrics> = LazyLock::new(ClientMetrics::new);
+            &METRICS
+        }
+    }
+
+    fn node_label(url: &Url) -> KeyValue {
+        let host = url.host_str().unwrap_or_default();
+        let node = match url.port_or_known_default() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        };
+        KeyValue::new("node", node)
+    }
+
+    fn error_kind(err: &Error) -> &'static str {
+        match err {
+            Error::Connect { .. } => "connect_error",
+            Error::Timeout { .. } => "timeout",
+            Error::RedirectLoop { .. } => "redirect_loop",
+            Error::Decode { .. } => "decode_error",
+            _ => "transport_error",
+        }
+    }
+
+    /// A request that got a response, recorded once the response and any stream of its value
+    /// are dropped, so that its duration covers reading the value.
+    #[derive(Clone)]
+    pub(crate) struct RequestRecord(Arc<PendingRequest>);
+
+    struct PendingRequest {
+        method: Method,
+        url: Url,
+        status: StatusCode,
+        start: Instant,
+        bytes: AtomicU64,
+    }
+
+    impl RequestRecord {
+        pub(crate) fn new(
+            method: Method,
+            url: Url,
+            status: StatusCode,
+            start: Instant,
+            sent: u64,
+        ) -> Self {
+            Self(Arc::new(PendingRequest {
+                method,
+                url,
+                status,
+                start,
+                bytes: AtomicU64::new(sent),
+            }))
+        }
+
+        /// Count value bytes read from the response.
+        pub(crate) fn add_received(&self, bytes: u64) {
+            self.0.bytes.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+
+    impl Drop for PendingRequest {
+        fn drop(&mut self) {
+            record_request(
+                &self.method,
+                &self.url,
+                Ok(self.status),
+                self.start.elapsed(),
+                *self.bytes.get_mut(),
+            );
+        }
+    }
+
+    /// Record a request to a node, with its status or the error that prevented a response.
+    pub(crate) fn record_request(
+        method: &Method,
+        url: &Url,
+        status: Result<StatusCode, &Error>,
+        elapsed: Duration,
+        bytes: u64,
+    ) {
+        let metrics = ClientMetrics::get();
+        let status = match status {
+            Ok(status) => status.as_str().to_string(),
+            Err(err) => error_kind(err).to_string(),
+        };
+        let labels = [
+            KeyValue::new("operation", method.as_str().to_lowercase()),
+            KeyValue::new("status", status),
+            node_label(url),
+        ];
+        metrics.count.add(1, &labels);
+        metrics.duration.record(elapsed.as_secs_f64(), &labels);
+        metrics
+            .bytes
+            .add(bytes, &[labels[0].clone(), labels[2].clone()]);
+    }
+
+    pub(crate) fn record_redirect(url: &Url) {
+        ClientMetrics::get().redirects.add(1, &[node_label(url)]);
+    }
+
+    pub(crate) fn record_route_refresh(success: bool) {
+        let status = if success { "ok" } else { "error" };
+        ClientMetrics::get()
+            .route_refreshes
+            .add(1, &[KeyValue::new("status", status)]);
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+mod noop {
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use reqwest::Method;
+    use reqwest::StatusCode;
+    use reqwest::Url;
+
+    use crate::Error;
+
+    #[derive(Clone)]
+    pub(crate) struct RequestRecord;
+
+    impl RequestRecord {
+        pub(crate) fn new(
+            _method: Method,
+            _url: Url,
+            _status: StatusCode,
+            _start: Instant,
+            _sent: u64,
+        ) -> Self {
+            Self
+        }
+
+        pub(crate) fn add_received(&self, _bytes: u64) {}
+    }
+
+    pub(crate) fn record_request(
+        _method: &Method,
+        _url: &Url,
+        _status: Result<StatusCode, &Error>,
+        _elapsed: Duration,
+        _bytes: u64,
+    ) {
+    }
+
+    pub(crate) fn record_redirect(_url: &Url) {}
+
+    pub(crate) fn record_route_refresh(_success: bool) {}
+}
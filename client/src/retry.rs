@@ -0,0 +1,116 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use backon::BackoffBuilder;
+use backon::ExponentialBuilder;
+use reqwest::StatusCode;
+
+use crate::Error;
+
+/// How a [`Client`](crate::Client) retries failed calls.
+///
+/// Reads and deletes are idempotent and retried on any [retryable](Error::is_retryable) error.
+/// Writes are only retried when the request provably did not reach the node, i.e. on connection
+/// failures and `429 Too Many Requests`, since a write retried after a timeout may land after a
+/// newer write from another client. Use [`RetryPolicy::with_idempotent_writes`] to lift this.
+///
+/// A write whose body is a stream is never retried, because the body is consumed by the first
+/// attempt.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    min_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    idempotent_writes: bool,
+}
+
+impl Default for RetryPolicy {
+    /// Up to 3 attempts with a jittered exponential backoff from 50ms up to 1s.
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            min_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(1),
+            jitter: true,
+            idempotent_writes: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that makes a single attempt per call.
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Set the maximum number of attempts per call, including the first one.
+    ///
+    /// Values below 1 are treated as 1.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Set the delay before the first retry and the cap of the exponential backoff.
+    pub fn with_backoff(mut self, min_backoff: Duration, max_backoff: Duration) -> Self {
+        self.min_backoff = min_backoff;
+        self.max_backoff = max_backoff.max(min_backoff);
+        self
+    }
+
+    /// Set whether to randomize backoff delays, which spreads out retries of many clients.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set whether writes are safe to retry on any retryable error, like reads.
+    pub fn with_idempotent_writes(mut self, idempotent_writes: bool) -> Self {
+        self.idempotent_writes = idempotent_writes;
+        self
+    }
+
+    /// Delays between attempts; yields `max_attempts - 1` items.
+    pub(crate) fn backoff(&self) -> impl Iterator<Item = Duration> + Send + use<> {
+        let builder = ExponentialBuilder::new()
+            .with_min_delay(self.min_backoff)
+            .with_max_delay(self.max_backoff)
+            .with_max_times(self.max_attempts - 1);
+        if self.jitter {
+            builder.with_jitter().build()
+        } else {
+            builder.build()
+        }
+    }
+
+    pub(crate) fn should_retry(&self, err: &Error, idempotent: bool) -> bool {
+        if !err.is_retryable() {
+            return false;
+        }
+        if idempotent || self.idempotent_writes {
+            return true;
+        }
+        match err {
+            Error::Connect { .. } => true,
+            Error::Status { status, .. } => *status == StatusCode::TOO_MANY_REQUESTS,
+            _ => false,
+        }
+    }
+}
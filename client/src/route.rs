@@ -12,42 +12,167 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::BTreeMap;
-use std::collections::btree_map::Entry;
+use std::collections::HashMap;
 
+use percas_ring::HashRing;
 use reqwest::Url;
+use serde::Deserialize;
 use uuid::Uuid;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MemberStatus {
+    Alive,
+    Dead,
+}
+
+/// A member as listed by `GET /members` of a control server.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Member {
+    node_id: Uuid,
+    advertise_data_url: Url,
+    advertise_ctrl_url: Url,
+    // absent on servers that predate reporting member status
+    #[serde(default = "alive")]
+    status: MemberStatus,
+    vnodes: Vec<u32>,
+}
+
+fn alive() -> MemberStatus {
+    MemberStatus::Alive
+}
+
+/// The response of `GET /members` of a control server.
+#[derive(Debug, Deserialize)]
+pub(crate) struct ListMembersResponse {
+    // absent on servers that predate ring epochs
+    #[serde(default)]
+    ring_hash: Option<u64>,
+    members: Vec<Member>,
+}
+
+#[derive(Debug, Clone)]
+struct Route {
+    data_url: Url,
+    status: MemberStatus,
+}
+
+/// The ring of a cluster, routing keys like the nodes do.
+///
+/// The ring is built from the virtual nodes each member reports, and a key is owned by the
+/// first alive member at or after it, as `percas_gossip::lookup_owner` on a node.
 #[derive(Default, Debug, Clone)]
 pub(crate) struct RouteTable {
-    ring: BTreeMap<u32, BTreeMap<Uuid, Url>>,
+    ring: HashRing<Uuid>,
+    routes: HashMap<Uuid, Route>,
+    ring_hash: Option<u64>,
+    ctrl_urls: Vec<Url>,
 }
 
 impl RouteTable {
-    pub(crate) fn insert(&mut self, hash: u32, node_id: Uuid, url: Url) {
-        match self.ring.entry(hash) {
-            Entry::Vacant(entry) => {
-                let mut map = BTreeMap::new();
-                map.insert(node_id, url);
-                entry.insert(map);
-            }
-            Entry::Occupied(mut entry) => {
-                entry.get_mut().insert(node_id, url);
-            }
+    pub(crate) fn new(resp: ListMembersResponse) -> Self {
+        let mut table = RouteTable {
+            ring_hash: resp.ring_hash,
+            ..RouteTable::default()
+        };
+        for member in resp.members {
+            table.ctrl_urls.push(member.advertise_ctrl_url);
+            table
+                .ring
+                .add_node_with_vnodes(member.node_id, member.vnodes);
+            let route = Route {
+                data_url: member.advertise_data_url,
+                status: member.status,
+            };
+            table.routes.insert(member.node_id, route);
         }
+        table
+    }
+
+    /// The content hash of the server ring this table was built from, if the server reports it.
+    pub(crate) fn ring_hash(&self) -> Option<u64> {
+        self.ring_hash
     }
 
-    pub(crate) fn lookup(&self, key: &str) -> Option<(&Uuid, &Url)> {
-        let hash = mur3::murmurhash3_x86_32(key.as_bytes(), 0);
-        self.ring
-            .range(hash..)
-            .next()
-            .and_then(|(_, nodes)| nodes.iter().next())
-            .or_else(|| {
-                self.ring
-                    .iter()
-                    .next()
-                    .and_then(|(_, nodes)| nodes.iter().next())
-            })
+    /// The control server urls of the members this table was built from.
+    pub(crate) fn ctrl_urls(&self) -> &[Url] {
+        &self.ctrl_urls
+    }
+
+    /// The alive member owning `key`.
+    pub(crate) fn lookup(&self, key: &str) -> Option<(Uuid, &Url)> {
+        let node_id = self.ring.lookup_until(key, |node_id| {
+            self.routes
+                .get(node_id)
+                .is_some_and(|route| route.status == MemberStatus::Alive)
+        })?;
+        Some((node_id, &self.routes[&node_id].data_url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::Timestamp;
+    use percas_gossip::MemberState;
+    use percas_gossip::Membership;
+    use percas_gossip::NodeInfo;
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use serde_json::json;
+
+    use super::*;
+
+    fn random_membership(rng: &mut StdRng) -> Membership {
+        let mut membership = Membership::default();
+        for i in 0..rng.random_range(1..8) {
+            let status = if rng.random_bool(0.3) {
+                percas_gossip::MemberStatus::Dead
+            } else {
+                percas_gossip::MemberStatus::Alive
+            };
+            let data_url = Url::parse(&format!("http://node-{i}:7654/")).unwrap();
+            let ctrl_url = Url::parse(&format!("http://node-{i}:7655/")).unwrap();
+            let node_id = Uuid::from_u128(rng.random());
+            membership.update_member(MemberState {
+                info: NodeInfo::new(node_id, "cluster".to_string(), data_url, ctrl_url),
+                status,
+                heartbeat: Timestamp::now(),
+            });
+        }
+        membership
+    }
+
+    #[test]
+    fn test_lookup_matches_server() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..100 {
+            // the ring and the member list as a node builds and serves them
+            let membership = random_membership(&mut rng);
+            let ring = HashRing::from(membership.members().keys().cloned());
+            let members = membership
+                .members()
+                .values()
+                .map(|m| {
+                    json!({
+                        "node_id": m.info.node_id,
+                        "advertise_data_url": m.info.advertise_data_url,
+                        "advertise_ctrl_url": m.info.advertise_ctrl_url,
+                        "incarnation": m.info.incarnation,
+                        "status": m.status,
+                        "vnodes": ring.list_vnodes(&m.info.node_id),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let resp = json!({ "ring_hash": 0, "members": members });
+            let table = RouteTable::new(serde_json::from_value(resp).unwrap());
+
+            for _ in 0..100 {
+                let key = format!("key-{}", rng.random::<u64>());
+                let expected = percas_gossip::lookup_owner(&ring, &membership, &key)
+                    .map(|m| (m.info.node_id, &m.info.advertise_data_url));
+                assert_eq!(table.lookup(&key), expected, "{key}");
+            }
+        }
     }
 }
@@ -0,0 +1,285 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::pin::pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use arc_swap::ArcSwapOption;
+use fastrace::Span;
+use fastrace::future::FutureExt as _;
+use fastrace_reqwest::traceparent_headers;
+use futures_util::future::Either;
+use futures_util::future::select;
+use reqwest::StatusCode;
+use reqwest::Url;
+use tokio::sync::Notify;
+
+use crate::Error;
+use crate::metrics;
+use crate::route::ListMembersResponse;
+use crate::route::RouteTable;
+
+// minimal interval between refreshes triggered by a stale route table
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+// the content hash of the ring a data request was routed with on the server
+const RING_HASH_HEADER: &str = "x-percas-ring-hash";
+
+/// How the route table is kept up to date.
+#[derive(Debug, Clone)]
+pub(crate) struct RefreshOptions {
+    pub(crate) interval: Duration,
+    pub(crate) jitter: Duration,
+    pub(crate) watch: bool,
+}
+
+/// Keeps the route table of a client, refreshed from the control servers in the background.
+///
+/// Requests read the table without blocking. When no control server can be reached, the last
+/// good table stays in use.
+pub(crate) struct Router {
+    client: reqwest::Client,
+    ctrl_urls: Vec<Url>,
+    options: RefreshOptions,
+    table: ArcSwapOption<RouteTable>,
+    stale: Notify,
+    refresh_lock: tokio::sync::Mutex<()>,
+    // bumped on every installed table, so that queued refreshes can be skipped
+    generation: AtomicU64,
+    last_attempt: Mutex<Option<Instant>>,
+    // cleared once a control server answers a watch right away with an unchanged ring
+    watch_supported: AtomicBool,
+}
+
+impl Router {
+    pub(crate) fn new(
+        client: reqwest::Client,
+        ctrl_urls: Vec<Url>,
+        options: RefreshOptions,
+    ) -> Self {
+        Self {
+            client,
+            ctrl_urls,
+            options,
+            table: ArcSwapOption::empty(),
+            stale: Notify::new(),
+            refresh_lock: tokio::sync::Mutex::new(()),
+            generation: AtomicU64::new(0),
+            last_attempt: Mutex::new(None),
+            watch_supported: AtomicBool::new(true),
+        }
+    }
+
+    pub(crate) fn ctrl_urls(&self) -> &[Url] {
+        &self.ctrl_urls
+    }
+
+    pub(crate) fn table(&self) -> Option<Arc<RouteTable>> {
+        self.table.load_full()
+    }
+
+    /// Mark the route table stale if the request was redirected to another node, or the server
+    /// routed it with a different ring.
+    pub(crate) fn observe_response(&self, url: &Url, resp: &reqwest::Response) {
+        if resp.url() != url {
+            metrics::record_redirect(url);
+            self.mark_stale();
+            return;
+        }
+
+        let Some(ring_hash) = resp
+            .headers()
+            .get(RING_HASH_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+        else {
+            return;
+        };
+
+        if let Some(table) = &*self.table.load()
+            && table.ring_hash() != Some(ring_hash)
+        {
+            self.mark_stale();
+        }
+    }
+
+    /// Ask the background task to refresh the route table soon.
+    pub(crate) fn mark_stale(&self) {
+        self.stale.notify_one();
+    }
+
+    /// Refresh the route table now, unless it was attempted within the minimal interval.
+    pub(crate) async fn refresh_if_due(&self) -> Result<(), Error> {
+        if self.until_due().is_zero() {
+            self.refresh().await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Refresh the route table from the first control server that answers.
+    ///
+    /// Concurrent callers share a single refresh.
+    async fn refresh(&self) -> Result<(), Error> {
+        let generation = self.generation.load(Ordering::Acquire);
+        let _guard = self.refresh_lock.lock().await;
+        if self.generation.load(Ordering::Acquire) != generation {
+            return Ok(());
+        }
+
+        *self.last_attempt.lock().unwrap() = Some(Instant::now());
+        let table = self.fetch(None).await?;
+        self.install(table);
+        Ok(())
+    }
+
+    /// Wait for the ring to change from `ring_hash` and install the new table.
+    async fn watch(&self, ring_hash: u64) -> Result<(), Error> {
+        let start = Instant::now();
+        let table = self.fetch(Some(ring_hash)).await?;
+        if table.ring_hash() == Some(ring_hash) && start.elapsed() < MIN_REFRESH_INTERVAL {
+            // the server does not hold the request until the ring changes
+            self.watch_supported.store(false, Ordering::Relaxed);
+        }
+        *self.last_attempt.lock().unwrap() = Some(Instant::now());
+        self.install(table);
+        Ok(())
+    }
+
+    /// Refresh the route table until the client is dropped.
+    pub(crate) async fn run(self: Arc<Self>) {
+        loop {
+            if self.table.load().is_none() {
+                // bootstrap; retry at the minimal interval until a control server answers
+                tokio::time::sleep(self.until_due()).await;
+                let _ = self.refresh().await;
+                continue;
+            }
+
+            let stale = pin!(self.stale.notified());
+            let scheduled = pin!(async {
+                let ring_hash = self
+                    .table
+                    .load()
+                    .as_ref()
+                    .and_then(|table| table.ring_hash());
+                if self.options.watch
+                    && self.watch_supported.load(Ordering::Relaxed)
+                    && let Some(ring_hash) = ring_hash
+                    && self.watch(ring_hash).await.is_ok()
+                {
+                    return false;
+                }
+                tokio::time::sleep(self.next_interval()).await;
+                true
+            });
+
+            let should_refresh = match select(stale, scheduled).await {
+                Either::Left(((), _)) => {
+                    tokio::time::sleep(self.until_due()).await;
+                    true
+                }
+                Either::Right((should_refresh, _)) => should_refresh,
+            };
+            if should_refresh {
+                // keep the last good table if no control server answers
+                let _ = self.refresh().await;
+            }
+        }
+    }
+
+    fn next_interval(&self) -> Duration {
+        let jitter = self.options.jitter.as_millis() as u64;
+        let jitter = Duration::from_millis(rand::random_range(0..=jitter));
+        self.options.interval + jitter
+    }
+
+    fn until_due(&self) -> Duration {
+        match *self.last_attempt.lock().unwrap() {
+            Some(last_attempt) => MIN_REFRESH_INTERVAL.saturating_sub(last_attempt.elapsed()),
+            None => Duration::ZERO,
+        }
+    }
+
+    fn install(&self, table: RouteTable) {
+        self.table.store(Some(Arc::new(table)));
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Fetch the route table from the configured control servers, then from those of the
+    /// members known by the last good table.
+    async fn fetch(&self, watch: Option<u64>) -> Result<RouteTable, Error> {
+        let span = Span::enter_with_local_parent("client.route_refresh");
+        let result = self.fetch_from_any(watch).in_span(span).await;
+        metrics::record_route_refresh(result.is_ok());
+        result
+    }
+
+    async fn fetch_from_any(&self, watch: Option<u64>) -> Result<RouteTable, Error> {
+        let mut ctrl_urls = self.ctrl_urls.clone();
+        if let Some(table) = &*self.table.load() {
+            for url in table.ctrl_urls() {
+                if !ctrl_urls.contains(url) {
+                    ctrl_urls.push(url.clone());
+                }
+            }
+        }
+
+        let mut last_err = None;
+        for ctrl_url in ctrl_urls {
+            let mut url = ctrl_url
+                .join("members")
+                .map_err(|err| Error::invalid_url(&ctrl_url, err))?;
+            if let Some(ring_hash) = watch {
+                url.query_pairs_mut()
+                    .append_pair("watch", &ring_hash.to_string());
+            }
+            match self.fetch_from(url.clone()).await {
+                Ok(table) => return Ok(table),
+                Err(err) => {
+                    last_err = Some(Error::RouteRefresh {
+                        url,
+                        source: Box::new(err),
+                    })
+                }
+            }
+        }
+        Err(last_err.expect("at least one control server url is configured"))
+    }
+
+    async fn fetch_from(&self, url: Url) -> Result<RouteTable, Error> {
+        let resp = self
+            .client
+            .get(url.clone())
+            .headers(traceparent_headers())
+            .send()
+            .await
+            .map_err(|err| Error::from_reqwest(url.clone(), err))?;
+
+        match resp.status() {
+            StatusCode::OK => resp
+                .json::<ListMembersResponse>()
+                .await
+                .map(RouteTable::new)
+                .map_err(|err| Error::from_reqwest(url, err)),
+            _ => Err(Error::from_status(url, resp).await),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use bytes::Bytes;
+use futures_util::Stream;
+use futures_util::TryStreamExt;
+use reqwest::Url;
+use tokio::io::AsyncRead;
+use tokio_util::io::StreamReader;
+
+use crate::Error;
+use crate::metrics::RequestRecord;
+
+/// A value read as a stream of byte chunks, see [`Client::get_stream`](crate::Client::get_stream).
+pub struct ValueStream {
+    url: Url,
+    content_length: Option<u64>,
+    inner: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>,
+    // records the request, with the bytes read, once the stream is dropped
+    record: Option<RequestRecord>,
+}
+
+impl fmt::Debug for ValueStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValueStream")
+            .field("url", &self.url)
+            .field("content_length", &self.content_length)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ValueStream {
+    pub(crate) fn new(url: Url, resp: reqwest::Response) -> Self {
+        Self {
+            url,
+            content_length: resp.content_length(),
+            record: resp.extensions().get::<RequestRecord>().cloned(),
+            inner: Box::pin(resp.bytes_stream()),
+        }
+    }
+
+    /// The size of the value, if the server reports it.
+    pub fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    /// Read the value through [`AsyncRead`], e.g. to copy it with [`tokio::io::copy`].
+    pub fn into_async_read(self) -> impl AsyncRead + Send + Unpin {
+        StreamReader::new(self.map_err(io::Error::other))
+    }
+}
+
+impl Stream for ValueStream {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let chunk = std::task::ready!(self.inner.as_mut().poll_next(cx));
+        if let (Some(Ok(chunk)), Some(record)) = (&chunk, &self.record) {
+            record.add_received(chunk.len() as u64);
+        }
+        Poll::Ready(
+            chunk.map(|chunk| chunk.map_err(|err| Error::from_reqwest(self.url.clone(), err))),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
@@ -0,0 +1,736 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An in-process mock of a Percas cluster, for testing code that uses the client.
+//!
+//! A [`MockServer`] serves the data and control HTTP API of a Percas node from a `HashMap`, so
+//! that no real node is needed. Faults such as latency, `429 Too Many Requests`, failures and
+//! redirects can be injected to exercise how callers handle retries and routing.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use percas_client::testing::Fault;
+//! use percas_client::testing::MockServer;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let server = MockServer::start().await.unwrap();
+//! let client = server.client_builder().build().unwrap();
+//!
+//! server.inject(Fault::too_many_requests().times(1));
+//! client.put("key", b"value").await.unwrap();
+//! assert_eq!(server.value("key").unwrap(), b"value");
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use percas_ring::HashRing;
+use poem::Body;
+use poem::EndpointExt;
+use poem::IntoResponse;
+use poem::Request;
+use poem::Response;
+use poem::Route;
+use poem::handler;
+use poem::http::Method;
+use poem::http::StatusCode;
+use poem::listener::Acceptor;
+use poem::listener::Listener;
+use poem::listener::TcpAcceptor;
+use poem::listener::TcpListener;
+use poem::web::Data;
+use poem::web::Json;
+use poem::web::Path;
+use poem::web::Query;
+use poem::web::headers::ETag;
+use poem::web::headers::HeaderMapExt;
+use poem::web::headers::IfNoneMatch;
+use reqwest::Url;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::ClientBuilder;
+use crate::protos::Version;
+
+// how long a watch on /members is held when the ring does not change
+const WATCH_MEMBERS_TIMEOUT: Duration = Duration::from_secs(30);
+const RING_HASH_HEADER: &str = "x-percas-ring-hash";
+
+/// A fault injected into the requests a [`MockServer`] serves.
+///
+/// Faults apply to data requests, in the order they are injected, to every request unless
+/// limited with [`Fault::times`]. A request is delayed by all matching latencies, then answered
+/// by the first matching status or redirect.
+#[derive(Debug, Clone)]
+pub struct Fault {
+    kind: FaultKind,
+    times: Option<usize>,
+    ctrl: bool,
+}
+
+#[derive(Debug, Clone)]
+enum FaultKind {
+    Latency(Duration),
+    Status(StatusCode),
+    Redirect(Url),
+}
+
+impl Fault {
+    /// Delay requests by the given duration before serving them.
+    pub fn latency(latency: Duration) -> Self {
+        Self::new(FaultKind::Latency(latency))
+    }
+
+    /// Answer requests with the given status instead of serving them.
+    pub fn status(status: StatusCode) -> Self {
+        Self::new(FaultKind::Status(status))
+    }
+
+    /// Answer requests with `429 Too Many Requests`, as a node shedding load does.
+    pub fn too_many_requests() -> Self {
+        Self::status(StatusCode::TOO_MANY_REQUESTS)
+    }
+
+    /// Answer requests with `500 Internal Server Error`, as a node failing to serve them does.
+    pub fn internal_error() -> Self {
+        Self::status(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    /// Redirect data requests to the same key on the node of the given data url, as a node that
+    /// does not own the key does.
+    pub fn redirect(data_url: Url) -> Self {
+        Self::new(FaultKind::Redirect(data_url))
+    }
+
+    /// Only inject the fault into the next `times` requests.
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = Some(times);
+        self
+    }
+
+    /// Inject the fault into control requests instead of data requests.
+    pub fn on_ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    fn new(kind: FaultKind) -> Self {
+        Self {
+            kind,
+            times: None,
+            ctrl: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum MemberStatus {
+    Alive,
+    Dead,
+}
+
+#[derive(Debug, Clone)]
+struct Member {
+    data_url: Url,
+    ctrl_url: Url,
+    status: MemberStatus,
+}
+
+/// The membership shared by the nodes of a mock cluster.
+#[derive(Debug)]
+struct Cluster {
+    members: Mutex<BTreeMap<Uuid, Member>>,
+    // the hash of the membership, updated on every change
+    ring_hash: tokio::sync::watch::Sender<u64>,
+}
+
+impl Cluster {
+    fn new() -> Self {
+        Self {
+            members: Mutex::new(BTreeMap::new()),
+            ring_hash: tokio::sync::watch::Sender::new(0),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut BTreeMap<Uuid, Member>)) {
+        let mut members = self.members.lock().unwrap();
+        f(&mut members);
+
+        let mut buf = Vec::new();
+        for (node_id, member) in members.iter() {
+            buf.extend_from_slice(node_id.as_bytes());
+            buf.push(member.status as u8);
+        }
+        let (ring_hash, _) = mur3::murmurhash3_x64_128(&buf, 0);
+        self.ring_hash.send_replace(ring_hash);
+    }
+
+    fn ring_hash(&self) -> u64 {
+        *self.ring_hash.borrow()
+    }
+
+    /// The data url of the alive member owning the key, like `Proxy::route` of a Percas node.
+    fn route(&self, key: &str) -> Option<(Uuid, Url)> {
+        let members = self.members.lock().unwrap();
+        let ring = HashRing::from(members.keys().copied());
+        let node_id = ring.lookup_until(key, |node_id| {
+            members[node_id].status == MemberStatus::Alive
+        })?;
+        Some((node_id, members[&node_id].data_url.clone()))
+    }
+}
+
+/// The state of a single mock node.
+#[derive(Debug)]
+struct Node {
+    node_id: Uuid,
+    cluster: Arc<Cluster>,
+    values: Mutex<HashMap<String, Vec<u8>>>,
+    faults: Mutex<Vec<Fault>>,
+    requests: AtomicU64,
+}
+
+impl Node {
+    /// Apply the injected faults, returning the response of a fault that answers the request.
+    async fn inject_faults(&self, key: Option<&str>) -> Option<Response> {
+        let mut latency = Duration::ZERO;
+        let mut answer = None;
+        {
+            let mut faults = self.faults.lock().unwrap();
+            for fault in faults.iter_mut() {
+                if fault.ctrl == key.is_some() || fault.times == Some(0) {
+                    continue;
+                }
+                let resp = match &fault.kind {
+                    FaultKind::Latency(delay) => {
+                        latency += *delay;
+                        None
+                    }
+                    FaultKind::Status(_) | FaultKind::Redirect(_) if answer.is_some() => continue,
+                    FaultKind::Status(status) => Some(status_response(*status)),
+                    FaultKind::Redirect(data_url) => match key.map(|key| data_url.join(key)) {
+                        Some(Ok(location)) => Some(redirect(&location)),
+                        _ => continue,
+                    },
+                };
+                answer = answer.or(resp);
+                if let Some(times) = &mut fault.times {
+                    *times -= 1;
+                }
+            }
+            faults.retain(|fault| fault.times != Some(0));
+        }
+
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        answer
+    }
+}
+
+fn status_response(status: StatusCode) -> Response {
+    Response::builder().status(status).body(status.to_string())
+}
+
+fn redirect(location: &Url) -> Response {
+    Response::builder()
+        .status(StatusCode::TEMPORARY_REDIRECT)
+        .header("Location", location.as_str())
+        .body(StatusCode::TEMPORARY_REDIRECT.to_string())
+}
+
+fn value_etag(value: &[u8]) -> ETag {
+    let (h1, h2) = mur3::murmurhash3_x64_128(value, 0);
+    format!("\"{h1:016x}{h2:016x}\"")
+        .parse()
+        .expect("hex digits make a valid entity tag")
+}
+
+#[handler]
+async fn data(
+    req: &Request,
+    Data(node): Data<&Arc<Node>>,
+    Path(key): Path<String>,
+    body: Body,
+) -> Response {
+    node.requests.fetch_add(1, Ordering::Relaxed);
+    let mut resp = serve_data(req, node, &key, body).await;
+    let ring_hash = node.cluster.ring_hash().to_string();
+    resp.headers_mut().insert(
+        RING_HASH_HEADER,
+        ring_hash.parse().expect("digits make a valid header value"),
+    );
+    resp
+}
+
+async fn serve_data(req: &Request, node: &Node, key: &str, body: Body) -> Response {
+    if let Some(resp) = node.inject_faults(Some(key)).await {
+        return resp;
+    }
+    if let Some((owner, data_url)) = node.cluster.route(key)
+        && owner != node.node_id
+    {
+        return match data_url.join(key) {
+            Ok(location) => redirect(&location),
+            Err(_) => status_response(StatusCode::BAD_REQUEST),
+        };
+    }
+
+    match *req.method() {
+        Method::GET => {
+            let values = node.values.lock().unwrap();
+            let Some(value) = values.get(key) else {
+                return status_response(StatusCode::NOT_FOUND);
+            };
+            let etag = value_etag(value);
+            let if_none_match = req.headers().typed_get::<IfNoneMatch>();
+            if if_none_match.is_some_and(|cond| !cond.precondition_passes(&etag)) {
+                return Response::builder()
+                    .status(StatusCode::NOT_MODIFIED)
+                    .typed_header(etag)
+                    .finish();
+            }
+            Response::builder()
+                .status(StatusCode::OK)
+                .typed_header(etag)
+                .body(value.clone())
+        }
+        Method::PUT => {
+            let Ok(value) = body.into_vec().await else {
+                return status_response(StatusCode::BAD_REQUEST);
+            };
+            node.values.lock().unwrap().insert(key.to_string(), value);
+            status_response(StatusCode::CREATED)
+        }
+        Method::DELETE => {
+            node.values.lock().unwrap().remove(key);
+            Response::builder().status(StatusCode::NO_CONTENT).finish()
+        }
+        _ => status_response(StatusCode::METHOD_NOT_ALLOWED),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MemberResponse {
+    node_id: Uuid,
+    advertise_data_url: Url,
+    advertise_ctrl_url: Url,
+    incarnation: u64,
+    status: MemberStatus,
+    vnodes: Vec<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct ListMembersResponse {
+    ring_hash: u64,
+    members: Vec<MemberResponse>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ListMembersParams {
+    #[serde(default)]
+    watch: Option<u64>,
+}
+
+#[handler]
+async fn list_members(
+    Data(node): Data<&Arc<Node>>,
+    Query(params): Query<ListMembersParams>,
+) -> Response {
+    if let Some(resp) = node.inject_faults(None).await {
+        return resp;
+    }
+
+    if let Some(ring_hash) = params.watch {
+        let mut changes = node.cluster.ring_hash.subscribe();
+        let changed = changes.wait_for(|current| *current != ring_hash);
+        let _ = tokio::time::timeout(WATCH_MEMBERS_TIMEOUT, changed).await;
+    }
+
+    let members = node.cluster.members.lock().unwrap();
+    let ring = HashRing::from(members.keys().copied());
+    let resp = ListMembersResponse {
+        ring_hash: node.cluster.ring_hash(),
+        members: members
+            .iter()
+            .map(|(node_id, member)| MemberResponse {
+                node_id: *node_id,
+                advertise_data_url: member.data_url.clone(),
+                advertise_ctrl_url: member.ctrl_url.clone(),
+                incarnation: 0,
+                status: member.status,
+                vnodes: ring.list_vnodes(node_id),
+            })
+            .collect(),
+    };
+    Json(resp).into_response()
+}
+
+#[handler]
+async fn fetch_version(Data(node): Data<&Arc<Node>>) -> Response {
+    if let Some(resp) = node.inject_faults(None).await {
+        return resp;
+    }
+
+    let version = env!("CARGO_PKG_VERSION");
+    Json(Version {
+        branch: "mock".to_string(),
+        commit: "mock".to_string(),
+        commit_short: "mock".to_string(),
+        clean: true,
+        source_time: String::new(),
+        build_time: String::new(),
+        rustc: String::new(),
+        target: String::new(),
+        version: version.to_string(),
+    })
+    .into_response()
+}
+
+/// A mock Percas node serving the data and control HTTP API in process.
+///
+/// It serves `GET`, `PUT` and `DELETE` of `/{key}` on its data url, with entity tags, and
+/// `GET /members`, including watches, and `GET /version` on its control url. Keys owned by
+/// another member of its cluster are redirected to that member, like a Percas node does.
+///
+/// The node is stopped when dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    node: Arc<Node>,
+    data_url: Url,
+    ctrl_url: Url,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        self.node
+            .cluster
+            .update(|members| members.retain(|node_id, _| *node_id != self.node.node_id));
+    }
+}
+
+impl MockServer {
+    /// Start a single node cluster on ephemeral ports of localhost.
+    pub async fn start() -> io::Result<Self> {
+        Self::start_in(Arc::new(Cluster::new())).await
+    }
+
+    /// Start a cluster of `nodes` nodes sharing one membership, so that each node redirects the
+    /// keys owned by the others.
+    pub async fn start_cluster(nodes: usize) -> io::Result<Vec<Self>> {
+        let cluster = Arc::new(Cluster::new());
+        let mut servers = Vec::with_capacity(nodes);
+        for _ in 0..nodes {
+            servers.push(Self::start_in(cluster.clone()).await?);
+        }
+        Ok(servers)
+    }
+
+    async fn start_in(cluster: Arc<Cluster>) -> io::Result<Self> {
+        let node = Arc::new(Node {
+            node_id: Uuid::now_v7(),
+            cluster,
+            values: Mutex::new(HashMap::new()),
+            faults: Mutex::new(Vec::new()),
+            requests: AtomicU64::new(0),
+        });
+
+        let (data_acceptor, data_url) = bind().await?;
+        let (ctrl_acceptor, ctrl_url) = bind().await?;
+        let data_route = Route::new()
+            .at(
+                "/*key",
+                poem::get(data).put(data).delete(data).data(node.clone()),
+            )
+            .boxed();
+        let ctrl_route = Route::new()
+            .at("/members", poem::get(list_members).data(node.clone()))
+            .at("/version", poem::get(fetch_version).data(node.clone()))
+            .boxed();
+        let tasks = [(data_acceptor, data_route), (ctrl_acceptor, ctrl_route)]
+            .into_iter()
+            .map(|(acceptor, route)| {
+                tokio::spawn(async move {
+                    let _ = poem::Server::new_with_acceptor(acceptor).run(route).await;
+                })
+            })
+            .collect();
+
+        node.cluster.update(|members| {
+            let member = Member {
+                data_url: data_url.clone(),
+                ctrl_url: ctrl_url.clone(),
+                status: MemberStatus::Alive,
+            };
+            members.insert(node.node_id, member);
+        });
+        Ok(Self {
+            node,
+            data_url,
+            ctrl_url,
+            tasks,
+        })
+    }
+
+    /// The id of this node.
+    pub fn node_id(&self) -> Uuid {
+        self.node.node_id
+    }
+
+    /// The url serving the data API.
+    pub fn data_url(&self) -> &Url {
+        &self.data_url
+    }
+
+    /// The url serving the control API.
+    pub fn ctrl_url(&self) -> &Url {
+        &self.ctrl_url
+    }
+
+    /// A client builder that talks to this node.
+    pub fn client_builder(&self) -> ClientBuilder {
+        ClientBuilder::new(self.data_url.as_str(), self.ctrl_url.as_str())
+    }
+
+    /// The value stored on this node for the given key.
+    pub fn value(&self, key: &str) -> Option<Vec<u8>> {
+        self.node.values.lock().unwrap().get(key).cloned()
+    }
+
+    /// Store a value on this node, bypassing routing and faults.
+    pub fn insert(&self, key: impl Into<String>, value: impl Into<Vec<u8>>) {
+        let mut values = self.node.values.lock().unwrap();
+        values.insert(key.into(), value.into());
+    }
+
+    /// Remove all values stored on this node.
+    pub fn clear(&self) {
+        self.node.values.lock().unwrap().clear();
+    }
+
+    /// The number of data requests this node received, including those answered by a fault.
+    pub fn requests(&self) -> u64 {
+        self.node.requests.load(Ordering::Relaxed)
+    }
+
+    /// Inject a fault into the requests this node serves.
+    pub fn inject(&self, fault: Fault) {
+        self.node.faults.lock().unwrap().push(fault);
+    }
+
+    /// Remove all injected faults.
+    pub fn clear_faults(&self) {
+        self.node.faults.lock().unwrap().clear();
+    }
+
+    /// Add a member that is not served by any mock to the cluster, and return its id.
+    ///
+    /// Keys it owns are redirected to its data url while it is alive, so the url may point to a
+    /// node that is down to test how failures are handled.
+    pub fn add_fake_member(&self, data_url: Url, ctrl_url: Url) -> Uuid {
+        let node_id = Uuid::now_v7();
+        self.node.cluster.update(|members| {
+            let member = Member {
+                data_url,
+                ctrl_url,
+                status: MemberStatus::Alive,
+            };
+            members.insert(node_id, member);
+        });
+        node_id
+    }
+
+    /// Mark a member of the cluster alive or dead. Keys owned by a dead member are served by the
+    /// next alive member on the ring, like in a Percas cluster.
+    pub fn set_member_alive(&self, node_id: Uuid, alive: bool) {
+        self.node.cluster.update(|members| {
+            if let Some(member) = members.get_mut(&node_id) {
+                member.status = if alive {
+                    MemberStatus::Alive
+                } else {
+                    MemberStatus::Dead
+                };
+            }
+        });
+    }
+}
+
+async fn bind() -> io::Result<(TcpAcceptor, Url)> {
+    let acceptor = TcpListener::bind("127.0.0.1:0").into_acceptor().await?;
+    let addr = acceptor.local_addr()[0]
+        .as_socket_addr()
+        .cloned()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::AddrNotAvailable, "no local address"))?;
+    let url = Url::parse(&format!("http://{addr}/")).map_err(io::Error::other)?;
+    Ok((acceptor, url))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::Error;
+    use crate::HedgePolicy;
+    use crate::RequestOptions;
+    use crate::RetryPolicy;
+
+    #[tokio::test]
+    async fn test_faults() {
+        let server = MockServer::start().await.unwrap();
+        let client = server
+            .client_builder()
+            .retry_policy(RetryPolicy::default().with_backoff(Duration::ZERO, Duration::ZERO))
+            .build()
+            .unwrap();
+
+        server.inject(Fault::too_many_requests().times(2));
+        client.put("key", b"value").await.unwrap();
+        assert_eq!(server.requests(), 3);
+        assert_eq!(client.get("key").await.unwrap().unwrap(), b"value");
+
+        server.inject(Fault::internal_error());
+        let err = client.get("key").await.unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::INTERNAL_SERVER_ERROR));
+        server.clear_faults();
+
+        server.inject(Fault::latency(Duration::from_secs(10)));
+        let options = RequestOptions::new().with_timeout(Duration::from_millis(100));
+        let err = client.get_with("key", &options).await.unwrap_err();
+        assert!(matches!(err, Error::DeadlineExceeded { .. }), "{err}");
+        server.clear_faults();
+
+        let version = client.version().await.unwrap();
+        assert_eq!(version.branch, "mock");
+    }
+
+    #[tokio::test]
+    async fn test_routing() {
+        let servers = MockServer::start_cluster(3).await.unwrap();
+        let client = servers[0].client_builder().build().unwrap();
+
+        let keys = (0..30).map(|i| format!("key-{i}")).collect::<Vec<_>>();
+        for key in &keys {
+            client.put(key, key.as_bytes()).await.unwrap();
+        }
+        let stored =
+            |server: &MockServer| keys.iter().filter(|k| server.value(k).is_some()).count();
+        assert_eq!(servers.iter().map(stored).sum::<usize>(), keys.len());
+        assert!(servers.iter().all(|server| stored(server) > 0));
+
+        // keys of a dead member are served by the next alive member
+        servers[0].set_member_alive(servers[1].node_id(), false);
+        for key in &keys {
+            client.put(key, b"again").await.unwrap();
+        }
+        assert!(
+            keys.iter()
+                .all(|k| servers[1].value(k).as_deref() != Some(b"again"))
+        );
+
+        // a redirect to another node is followed
+        let server = MockServer::start().await.unwrap();
+        let other = MockServer::start().await.unwrap();
+        other.insert("key", "value");
+        server.inject(Fault::redirect(other.data_url().clone()).times(1));
+        let client = server.client_builder().build().unwrap();
+        assert_eq!(client.get("key").await.unwrap().unwrap(), b"value");
+    }
+
+    #[tokio::test]
+    async fn test_streaming_redirect() {
+        fn stream(
+            value: &'static str,
+        ) -> impl futures_util::TryStream<Ok = Bytes, Error = io::Error> {
+            futures_util::stream::iter([Ok(Bytes::from_static(value.as_bytes()))])
+        }
+
+        let server = MockServer::start().await.unwrap();
+        let other = MockServer::start().await.unwrap();
+        let client = server.client_builder().build().unwrap();
+
+        // a streaming body cannot be replayed to follow a redirect
+        server.inject(Fault::redirect(other.data_url().clone()).times(1));
+        let err = client
+            .put_stream("key", stream("value"), None)
+            .await
+            .unwrap_err();
+        match &err {
+            Error::Misrouted { location, .. } => {
+                let expected = other.data_url().join("key").unwrap();
+                assert_eq!(location.as_deref(), Some(expected.as_str()));
+            }
+            _ => panic!("unexpected error: {err}"),
+        }
+        assert!(err.is_retryable());
+        assert!(other.value("key").is_none());
+
+        client
+            .put_stream("key", stream("value"), None)
+            .await
+            .unwrap();
+        assert_eq!(server.value("key").unwrap(), b"value");
+
+        server.inject(Fault::latency(Duration::from_secs(10)));
+        let options = RequestOptions::new().with_timeout(Duration::from_millis(100));
+        let err = client
+            .put_stream_with("key", stream("again"), None, &options)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::DeadlineExceeded { .. }), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_hedging() {
+        let server = MockServer::start().await.unwrap();
+        server.insert("key", "value");
+        let client = server
+            .client_builder()
+            .hedge_policy(HedgePolicy::new(0.9))
+            .build()
+            .unwrap();
+        // learn the usual latency
+        for _ in 0..64 {
+            client.get("key").await.unwrap();
+        }
+
+        // a read stuck on the node is overtaken by the hedged read
+        server.inject(Fault::latency(Duration::from_secs(5)).times(1));
+        let requests = server.requests();
+        let start = std::time::Instant::now();
+        assert_eq!(client.get("key").await.unwrap().unwrap(), b"value");
+        assert!(
+            start.elapsed() < Duration::from_secs(1),
+            "{:?}",
+            start.elapsed()
+        );
+        assert_eq!(server.requests(), requests + 2);
+    }
+}
@@ -105,6 +105,21 @@ pub fn load_config(config_file: PathBuf) -> Result<LoadConfigResult, Error> {
                 let value = toml_edit::value(value);
                 (path, value)
             }
+            "object" => {
+                // comma-separated `key=value` pairs, as in OTEL_EXPORTER_OTLP_HEADERS
+                let path = ent.ent_path;
+                let mut table = toml_edit::InlineTable::new();
+                for pair in v.split(',').filter(|pair| !pair.trim().is_empty()) {
+                    let Some((key, value)) = pair.split_once('=') else {
+                        bail!(Error(format!(
+                            "failed to parse key=value pair {pair} of key {k}"
+                        )))
+                    };
+                    table.insert(key.trim(), value.trim().into());
+                }
+                let value = toml_edit::value(table);
+                (path, value)
+            }
             ty => {
                 bail!(Error(format!(
                     "failed to parse environment variable {k} with value {v} and resolved type {ty}"
@@ -117,6 +132,9 @@ pub fn load_config(config_file: PathBuf) -> Result<LoadConfigResult, Error> {
 
     let config = Config::deserialize(config.into_deserializer())
         .or_raise(|| Error("failed to deserialize config".to_string()))?;
+    config
+        .validate()
+        .or_raise(|| Error("invalid config".to_string()))?;
     Ok(LoadConfigResult { config, warnings })
 }
 
@@ -197,4 +215,20 @@ mod tests {
             "http://192.168.1.14:4317"
         );
     }
+
+    #[sealed_test(env = [
+        ("PERCAS_CONFIG_TELEMETRY_OTLP_HEADERS", "authorization=Bearer token, x-tenant=percas"),
+    ])]
+    fn test_override_otlp_headers() {
+        let workspace = env!("CARGO_WORKSPACE_DIR");
+        let dev_config = load_config(PathBuf::from(format!(
+            "{workspace}/dev/standalone/config.toml"
+        )))
+        .unwrap()
+        .config;
+        let headers = dev_config.telemetry.otlp.headers;
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers["authorization"], "Bearer token");
+        assert_eq!(headers["x-tenant"], "percas");
+    }
 }
@@ -18,6 +18,7 @@ use parse_display::Display;
 use percas_version::version;
 
 mod config;
+mod node_id;
 mod start;
 mod styled;
 
@@ -32,6 +33,7 @@ impl Command {
     pub fn run(self) -> Result<(), Error> {
         match self.cmd {
             SubCommand::Start(cmd) => cmd.run(),
+            SubCommand::NodeId(cmd) => cmd.run(),
         }
     }
 }
@@ -40,6 +42,8 @@ impl Command {
 enum SubCommand {
     /// Start a Percas node.
     Start(start::CommandStart),
+    /// Print or reset the identity of a Percas node.
+    NodeId(node_id::CommandNodeId),
 }
 
 #[derive(Debug, Display)]
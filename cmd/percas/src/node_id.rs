@@ -0,0 +1,80 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+
+use clap::ValueHint;
+use exn::Result;
+use exn::ResultExt;
+use exn::bail;
+use percas_core::node_file_path;
+use percas_server::fence::Fence;
+use percas_server::server::load_node_id;
+
+use crate::Error;
+use crate::config::LoadConfigResult;
+use crate::config::load_config;
+
+#[derive(Debug, clap::Parser)]
+pub struct CommandNodeId {
+    #[clap(short, long, help = "Path to config file", value_hint = ValueHint::FilePath)]
+    config_file: PathBuf,
+    /// Forget the node identity so that a new one is generated on next start. The node must be
+    /// stopped; its cached data is kept and claimed by the new identity.
+    #[clap(long)]
+    reset: bool,
+}
+
+impl CommandNodeId {
+    pub fn run(self) -> Result<(), Error> {
+        let LoadConfigResult { config, .. } = load_config(self.config_file)?;
+        let server_config = &config.server;
+
+        if !self.reset {
+            let node_id = load_node_id(server_config)
+                .or_raise(|| Error("failed to load node id".to_string()))?;
+            match node_id {
+                Some(node_id) => println!("{node_id}"),
+                None => eprintln!("node id is not assigned yet; it is generated on first start"),
+            }
+            return Ok(());
+        }
+
+        let make_error = || Error("failed to reset node id".to_string());
+        if let Some(node_id) = server_config.node_id {
+            bail!(Error(format!(
+                "node id is pinned to {node_id} by server.node_id; change the config instead"
+            )));
+        }
+
+        let mut fence = Fence::lock_node_dir(&server_config.dir).or_raise(make_error)?;
+        let node_id = load_node_id(server_config).or_raise(make_error)?;
+        fence
+            .release_storage(&config.storage)
+            .or_raise(make_error)?;
+
+        let node_file_path = node_file_path(&server_config.dir);
+        if node_file_path.exists() {
+            std::fs::remove_file(&node_file_path).or_raise(make_error)?;
+        }
+
+        match node_id {
+            Some(node_id) => {
+                println!("reset node id {node_id}; a new one is generated on next start")
+            }
+            None => println!("node id is not assigned yet; nothing to reset"),
+        }
+        Ok(())
+    }
+}
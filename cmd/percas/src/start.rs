@@ -12,21 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use clap::ValueHint;
 use exn::Result;
 use exn::ResultExt;
-use mixtrics::registry::opentelemetry_0_31::OpenTelemetryMetricsRegistry;
 use percas_core::Config;
-use percas_core::FoyerEngine;
 use percas_core::Runtime;
 use percas_core::make_runtime;
 use percas_core::num_cpus;
+use percas_core::open_engine;
 use percas_metrics::GlobalMetrics;
 use percas_server::PercasContext;
+use percas_server::access_log::AccessLog;
+use percas_server::fence::Fence;
+use percas_server::hotkeys::HotKeys;
+use percas_server::server::load_node_id;
+use percas_server::server::load_or_create_node;
 use percas_server::server::make_acceptor_and_advertise_url;
 use percas_server::telemetry;
 use uuid::Uuid;
@@ -48,13 +51,19 @@ impl CommandStart {
     pub fn run(self) -> Result<(), Error> {
         let LoadConfigResult { config, warnings } = load_config(self.config_file)?;
 
-        let node_id = Uuid::now_v7();
+        // resolve the node identity before anything reports it, while holding the node dir
+        let make_error = || Error("failed to start server".to_string());
+        let fence = Fence::lock_node_dir(&config.server.dir).or_raise(make_error)?;
+        let node_id = load_node_id(&config.server)
+            .or_raise(make_error)?
+            .unwrap_or_else(Uuid::now_v7);
         let service_name = self.service_name.unwrap_or("percas".to_string()).leak();
 
         let telemetry_runtime = make_telemetry_runtime();
         let mut drop_guards = telemetry::init(
             &telemetry_runtime,
             service_name,
+            &config.server.cluster_id,
             node_id,
             config.telemetry.clone(),
         );
@@ -62,7 +71,7 @@ impl CommandStart {
         for warning in warnings {
             log::warn!("{warning}");
         }
-        log::info!("Percas is starting with loaded config: {config:#?}");
+        log::info!("Percas node {node_id} is starting with loaded config: {config:#?}");
 
         let io_runtime = make_io_runtime();
         let server_runtime = make_server_runtime();
@@ -71,6 +80,7 @@ impl CommandStart {
             &io_runtime,
             &server_runtime,
             &gossip_runtime,
+            fence,
             node_id,
             config,
         ))
@@ -102,32 +112,27 @@ async fn run_server(
     io_rt: &Runtime,
     server_rt: &Runtime,
     gossip_rt: &Runtime,
+    mut fence: Fence,
     node_id: Uuid,
     config: Config,
 ) -> Result<(), Error> {
     let make_error = || Error("failed to start server".to_string());
 
+    let report_interval = config.telemetry.report_interval().or_raise(make_error)?;
+    let slow_request_threshold = config
+        .telemetry
+        .diagnostics
+        .slow_request_threshold()
+        .or_raise(make_error)?;
+    let access_log = config
+        .telemetry
+        .access_log
+        .as_ref()
+        .map(|access_log| AccessLog::new(access_log, config.telemetry.diagnostics.hash_keys))
+        .transpose()
+        .or_raise(make_error)?
+        .map(Arc::new);
     let server_config = config.server;
-    fs::create_dir_all(&server_config.dir).or_raise(|| {
-        Error(format!(
-            "failed to create data dir: {}",
-            server_config.dir.display()
-        ))
-    })?;
-
-    let engine = FoyerEngine::try_new(
-        io_rt,
-        config.storage.data_dir.as_path(),
-        config.storage.memory_capacity.into(),
-        config.storage.disk_capacity.into(),
-        config.storage.disk_throttle,
-        Some(OpenTelemetryMetricsRegistry::new(
-            GlobalMetrics::get().meter.clone(),
-        )),
-    )
-    .await
-    .or_raise(make_error)?;
-    let ctx = Arc::new(PercasContext::new(engine));
 
     let (shutdown_tx, shutdown_rx) = mea::shutdown::new_pair();
 
@@ -145,14 +150,35 @@ async fn run_server(
     .await
     .or_raise(make_error)?;
 
+    let current_node = load_or_create_node(
+        &server_config,
+        node_id,
+        advertise_data_url.clone(),
+        advertise_ctrl_url.clone(),
+    )
+    .or_raise(make_error)?;
+    fence
+        .claim_storage(&config.storage, current_node.node_id)
+        .or_raise(make_error)?;
+
+    let engine = open_engine(
+        io_rt,
+        &config.storage,
+        Some(GlobalMetrics::get().meter.clone()),
+    )
+    .await
+    .or_raise(make_error)?;
+    let diagnostics = &config.telemetry.diagnostics;
+    let hot_keys = HotKeys::new(diagnostics.hot_keys, diagnostics.hash_keys);
+    let ctx = Arc::new(PercasContext::new(engine, hot_keys, diagnostics.hash_keys));
+
     let (gossip_state, gossip_futs) = percas_server::server::start_gossip(
         gossip_rt,
         shutdown_rx.clone(),
+        ctx.clone(),
         server_config,
-        node_id,
+        current_node,
         ctrl_acceptor,
-        advertise_data_url.clone(),
-        advertise_ctrl_url.clone(),
     )
     .await
     .or_raise(make_error)?;
@@ -166,6 +192,9 @@ async fn run_server(
         advertise_ctrl_url,
         gossip_state,
         gossip_futs,
+        report_interval,
+        slow_request_threshold,
+        access_log,
     )
     .await
     .or_raise(|| Error("A fatal error has occurred in server process.".to_string()))?;
@@ -174,5 +203,6 @@ async fn run_server(
         .or_raise(|| Error("failed to setup ctrl-c signal handle".to_string()))?;
 
     server.await_shutdown().await;
+    drop(fence);
     Ok(())
 }
@@ -19,7 +19,11 @@ use criterion::BenchmarkId;
 use criterion::Criterion;
 use criterion::criterion_group;
 use criterion::criterion_main;
+use percas_core::DeviceConfig;
+use percas_core::DeviceKind;
+use percas_core::Engine;
 use percas_core::FoyerEngine;
+use percas_core::IoEngineConfig;
 use rand::Rng;
 use tempfile::tempdir_in;
 
@@ -36,12 +40,19 @@ fn foyer_engine(c: &mut Criterion) {
     {
         let dir = tempdir_in("/tmp").unwrap();
         let engine = runtime.block_on(async {
+            let device = DeviceConfig {
+                kind: DeviceKind::Fs,
+                path: dir.path().to_path_buf(),
+                capacity: Some(ByteSize::gib(4).into()),
+                throttle: None,
+                force: false,
+            };
             FoyerEngine::try_new(
                 &test_rt,
-                dir.path(),
+                "foyer",
+                &device,
                 ByteSize::default(),
-                ByteSize::gib(4),
-                None,
+                &IoEngineConfig::default(),
                 None,
             )
             .await
@@ -54,7 +65,7 @@ fn foyer_engine(c: &mut Criterion) {
                 c.bench_with_input(BenchmarkId::new("put", bs), &payload, |b, s| {
                     b.to_async(&runtime).iter(|| async {
                         let key = gen_key(32);
-                        engine.put(&key, s);
+                        engine.put(&key, s).await.unwrap();
                     })
                 });
             });
@@ -66,12 +77,19 @@ fn foyer_engine(c: &mut Criterion) {
             .for_each(|bs| {
                 let dir = tempdir_in("/tmp").unwrap();
                 let engine = runtime.block_on(async {
+                    let device = DeviceConfig {
+                        kind: DeviceKind::Fs,
+                        path: dir.path().to_path_buf(),
+                        capacity: Some(ByteSize::gib(4).into()),
+                        throttle: None,
+                        force: false,
+                    };
                     FoyerEngine::try_new(
                         &test_rt,
-                        dir.path(),
+                        "foyer",
+                        &device,
                         ByteSize::default(),
-                        ByteSize::gib(4),
-                        None,
+                        &IoEngineConfig::default(),
                         None,
                     )
                     .await
@@ -79,13 +97,15 @@ fn foyer_engine(c: &mut Criterion) {
                 });
                 let payload = gen_payload(bs);
                 let keys = (0..1000).map(|_| gen_key(32)).collect::<Vec<_>>();
-                keys.iter().for_each(|key| {
-                    engine.put(key, &payload);
+                runtime.block_on(async {
+                    for key in &keys {
+                        engine.put(key, &payload).await.unwrap();
+                    }
                 });
                 c.bench_with_input(BenchmarkId::new("get", bs), &keys, |b, s| {
                     b.to_async(&runtime).iter(|| async {
                         let key = &s[rand::rng().random_range(0..keys.len())];
-                        std::hint::black_box(engine.get(key).await);
+                        std::hint::black_box(engine.get(key).await.unwrap());
                     })
                 });
             });
@@ -12,19 +12,32 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
+use std::num::NonZeroU32;
 use std::num::NonZeroUsize;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::Duration;
 
+use exn::Result;
+use exn::ResultExt;
+use exn::ensure;
+use parse_display::Display;
 use serde::Deserialize;
 use serde::Serialize;
 use url::Url;
+use uuid::Uuid;
 
 use crate::available_memory;
 use crate::newtype::ByteSize;
 use crate::newtype::DiskThrottle;
 
+#[derive(Debug, Display)]
+pub struct ConfigError(String);
+
+impl std::error::Error for ConfigError {}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[cfg_attr(test, derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
@@ -52,12 +65,168 @@ pub struct ServerConfig {
     pub initial_peers: Vec<Url>,
     #[serde(default = "default_cluster_id")]
     pub cluster_id: String,
+    /// Pins the identity of the node. By default, it is generated on first start and persisted
+    /// in `node.json` under `dir`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<Uuid>,
+    #[serde(default)]
+    pub gossip: GossipConfig,
+}
+
+/// Timing parameters of the gossip protocol.
+///
+/// Every parameter defaults to the value of the chosen `preset` and can be overridden
+/// individually.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct GossipConfig {
+    #[serde(default)]
+    pub preset: GossipPreset,
+    /// How often a random member is pinged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ping_interval: Option<jiff::SignedDuration>,
+    /// How often membership is reconciled with a random member.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_interval: Option<jiff::SignedDuration>,
+    /// The delay between retries of a failed gossip request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_interval: Option<jiff::SignedDuration>,
+    /// How many times a failed gossip request is retried before the peer is marked dead.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<usize>,
+    /// How often the hash ring is rebuilt from the membership.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rebuild_ring_interval: Option<jiff::SignedDuration>,
+    /// How long a dead member is kept before it is removed from the membership.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member_deadline: Option<jiff::SignedDuration>,
+    /// The timeout of a single gossip request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_timeout: Option<jiff::SignedDuration>,
+}
+
+/// Presets of gossip timing parameters for common environments.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum GossipPreset {
+    /// Fast convergence for local development clusters.
+    Dev,
+    /// Nodes in the same datacenter.
+    #[default]
+    Lan,
+    /// Nodes across datacenters or regions with higher and more variable latency.
+    Wan,
+}
+
+/// The effective gossip timing parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GossipTiming {
+    pub ping_interval: Duration,
+    pub sync_interval: Duration,
+    pub retry_interval: Duration,
+    pub retries: usize,
+    pub rebuild_ring_interval: Duration,
+    pub member_deadline: Duration,
+    pub request_timeout: Duration,
+}
+
+impl GossipPreset {
+    pub fn timing(&self) -> GossipTiming {
+        match self {
+            GossipPreset::Dev => GossipTiming {
+                ping_interval: Duration::from_millis(500),
+                sync_interval: Duration::from_secs(1),
+                retry_interval: Duration::from_millis(200),
+                retries: 2,
+                rebuild_ring_interval: Duration::from_secs(1),
+                member_deadline: Duration::from_secs(10),
+                request_timeout: Duration::from_secs(1),
+            },
+            GossipPreset::Lan => GossipTiming {
+                ping_interval: Duration::from_secs(1),
+                sync_interval: Duration::from_secs(5),
+                retry_interval: Duration::from_secs(1),
+                retries: 3,
+                rebuild_ring_interval: Duration::from_secs(5),
+                member_deadline: Duration::from_secs(30),
+                request_timeout: Duration::from_secs(3),
+            },
+            GossipPreset::Wan => GossipTiming {
+                ping_interval: Duration::from_secs(2),
+                sync_interval: Duration::from_secs(15),
+                retry_interval: Duration::from_secs(2),
+                retries: 5,
+                rebuild_ring_interval: Duration::from_secs(10),
+                member_deadline: Duration::from_secs(120),
+                request_timeout: Duration::from_secs(10),
+            },
+        }
+    }
+}
+
+impl GossipConfig {
+    /// Resolve the effective timing parameters and validate them.
+    pub fn timing(&self) -> Result<GossipTiming, ConfigError> {
+        fn resolve(
+            name: &str,
+            value: Option<jiff::SignedDuration>,
+            default: Duration,
+        ) -> Result<Duration, ConfigError> {
+            let Some(value) = value else {
+                return Ok(default);
+            };
+            let make_error = || ConfigError(format!("server.gossip.{name} must be positive"));
+            let value = Duration::try_from(value).or_raise(make_error)?;
+            ensure!(!value.is_zero(), make_error());
+            Ok(value)
+        }
+
+        let preset = self.preset.timing();
+        let timing = GossipTiming {
+            ping_interval: resolve("ping_interval", self.ping_interval, preset.ping_interval)?,
+            sync_interval: resolve("sync_interval", self.sync_interval, preset.sync_interval)?,
+            retry_interval: resolve("retry_interval", self.retry_interval, preset.retry_interval)?,
+            retries: self.retries.unwrap_or(preset.retries),
+            rebuild_ring_interval: resolve(
+                "rebuild_ring_interval",
+                self.rebuild_ring_interval,
+                preset.rebuild_ring_interval,
+            )?,
+            member_deadline: resolve(
+                "member_deadline",
+                self.member_deadline,
+                preset.member_deadline,
+            )?,
+            request_timeout: resolve(
+                "request_timeout",
+                self.request_timeout,
+                preset.request_timeout,
+            )?,
+        };
+
+        // a dead member must outlive the pings that may still refute its death
+        let min_deadline = timing.ping_interval * timing.retries.max(1) as u32;
+        ensure!(
+            timing.member_deadline > min_deadline,
+            ConfigError(format!(
+                "server.gossip.member_deadline ({:?}) must be greater than ping_interval * retries ({:?})",
+                timing.member_deadline, min_deadline
+            ))
+        );
+
+        Ok(timing)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[cfg_attr(test, derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 pub struct StorageConfig {
+    /// The storage engine that serves data requests.
+    #[serde(default)]
+    pub engine: StorageEngine,
     #[serde(default = "default_data_dir")]
     pub data_dir: PathBuf,
     #[serde(default = "default_disk_capacity")]
@@ -66,6 +235,162 @@ pub struct StorageConfig {
     pub memory_capacity: ByteSize,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub disk_throttle: Option<DiskThrottle>,
+    /// How the disk engine submits I/O; ignored by the memory engine.
+    #[serde(default)]
+    pub io_engine: IoEngineConfig,
+    /// The data devices of the disk engine, each with its own capacity and throttle. Keys are
+    /// sharded over them. When empty, `data_dir` is the only device.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<DeviceConfig>,
+    /// The faults injected by the `faulty` engine; ignored by the other engines.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub faults: Option<FaultsConfig>,
+}
+
+impl StorageConfig {
+    /// The data devices of the disk engine, falling back to `data_dir` if none is configured.
+    pub fn devices(&self) -> Vec<DeviceConfig> {
+        if self.devices.is_empty() {
+            vec![DeviceConfig {
+                kind: DeviceKind::Fs,
+                path: self.data_dir.clone(),
+                capacity: Some(self.disk_capacity.clone()),
+                throttle: self.disk_throttle.clone(),
+                force: false,
+            }]
+        } else {
+            self.devices.clone()
+        }
+    }
+
+    /// The engine that holds the data, which the `faulty` engine delegates to.
+    pub fn data_engine(&self) -> StorageEngine {
+        match self.engine {
+            StorageEngine::Faulty => self.faults.clone().unwrap_or_default().engine,
+            engine => engine,
+        }
+    }
+}
+
+/// Faults injected into data requests, to test clients and the cluster against a failing node.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct FaultsConfig {
+    /// The engine that serves the operations which do not fail; it cannot be `faulty`.
+    #[serde(default)]
+    pub engine: StorageEngine,
+    /// Fail one in this many operations on average. None fails when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_one_in: Option<NonZeroU32>,
+    /// The delay added to every operation.
+    #[serde(default)]
+    pub latency: jiff::SignedDuration,
+}
+
+impl FaultsConfig {
+    pub fn latency(&self) -> Result<Duration, ConfigError> {
+        Duration::try_from(self.latency)
+            .or_raise(|| ConfigError("storage.faults.latency must not be negative".to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct DeviceConfig {
+    /// Whether the device is a directory on a filesystem or a raw block device.
+    #[serde(default)]
+    pub kind: DeviceKind,
+    /// The directory that holds the data files, or the path of the block device.
+    pub path: PathBuf,
+    /// The capacity used on the device. Defaults to 512 MiB on a filesystem and to the whole
+    /// device for a block device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<ByteSize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub throttle: Option<DiskThrottle>,
+    /// Whether to use a block device even if it carries a filesystem or partition table, which
+    /// is then overwritten.
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// The kinds of data devices.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceKind {
+    /// A directory on a mounted filesystem.
+    #[default]
+    Fs,
+    /// A raw block device or partition without a filesystem.
+    Block,
+}
+
+/// The storage engines a node can run on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum StorageEngine {
+    /// A hybrid cache over memory and disk.
+    #[default]
+    Foyer,
+    /// A memory-only cache; nothing is written to `data_dir`.
+    Memory,
+    /// Another engine with the faults of `[storage.faults]` injected, for testing.
+    Faulty,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct IoEngineConfig {
+    /// The preferred I/O engine. io_uring falls back to psync if it cannot be set up.
+    #[serde(default)]
+    pub kind: IoEngineKind,
+    /// Whether io_uring polls the submission queue from a kernel thread. This saves syscalls at
+    /// the cost of a busy core per I/O thread.
+    #[serde(default = "default_io_engine_sqpoll")]
+    pub sqpoll: bool,
+    /// The io_uring queue depth of each I/O thread.
+    #[serde(default = "default_io_engine_io_depth")]
+    pub io_depth: usize,
+    /// Whether data files and block devices are opened with `O_DIRECT`, bypassing the page cache.
+    /// Only available on Linux; ignored elsewhere.
+    #[serde(default)]
+    pub direct: bool,
+}
+
+impl Default for IoEngineConfig {
+    fn default() -> Self {
+        Self {
+            kind: IoEngineKind::default(),
+            sqpoll: default_io_engine_sqpoll(),
+            io_depth: default_io_engine_io_depth(),
+            direct: false,
+        }
+    }
+}
+
+/// The I/O engines the disk engine can run on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum IoEngineKind {
+    /// Linux io_uring.
+    #[default]
+    IoUring,
+    /// Blocking `pread`/`pwrite` on a thread pool; available everywhere.
+    Psync,
+}
+
+const fn default_io_engine_sqpoll() -> bool {
+    true
+}
+
+const fn default_io_engine_io_depth() -> usize {
+    64
 }
 
 fn default_listen_data_addr() -> SocketAddr {
@@ -110,6 +435,16 @@ pub struct TelemetryConfig {
     pub traces: Option<TracesConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metrics: Option<MetricsConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_log: Option<AccessLogConfig>,
+    #[serde(default)]
+    pub diagnostics: DiagnosticsConfig,
+    #[serde(default)]
+    pub otlp: OtlpConfig,
+    /// Extra resource attributes attached to all exported telemetry, e.g. `zone`. The service
+    /// name, cluster id and node id are always attached.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub resource: BTreeMap<String, String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -149,6 +484,67 @@ pub struct OpentelemetryAppenderConfig {
     pub otlp_endpoint: String,
 }
 
+/// JSON lines, one per data request, written apart from the application logs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct AccessLogConfig {
+    pub dir: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<NonZeroUsize>,
+    /// Record one in this many requests; defaults to every request. Server errors are always
+    /// recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_one_in: Option<NonZeroU32>,
+}
+
+/// Hot key and slow request detection on the data path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct DiagnosticsConfig {
+    /// How many of the most requested keys are reported; 0 disables tracking.
+    #[serde(default = "default_hot_keys")]
+    pub hot_keys: usize,
+    /// Data requests taking longer than this are logged.
+    #[serde(default = "default_slow_request_threshold")]
+    pub slow_request_threshold: jiff::SignedDuration,
+    /// Write a hash of each key instead of the key itself to the access log, the application
+    /// logs and the hot key reports.
+    #[serde(default)]
+    pub hash_keys: bool,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            hot_keys: default_hot_keys(),
+            slow_request_threshold: default_slow_request_threshold(),
+            hash_keys: false,
+        }
+    }
+}
+
+const fn default_hot_keys() -> usize {
+    32
+}
+
+const fn default_slow_request_threshold() -> jiff::SignedDuration {
+    jiff::SignedDuration::from_millis(100)
+}
+
+impl DiagnosticsConfig {
+    /// Resolve the latency above which a data request is logged as slow.
+    pub fn slow_request_threshold(&self) -> Result<Duration, ConfigError> {
+        let make_error = || {
+            ConfigError("telemetry.diagnostics.slow_request_threshold must be positive".to_string())
+        };
+        let value = Duration::try_from(self.slow_request_threshold).or_raise(make_error)?;
+        ensure!(!value.is_zero(), make_error());
+        Ok(value)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[cfg_attr(test, derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
@@ -169,10 +565,77 @@ pub struct OpentelemetryTracesConfig {
 #[cfg_attr(test, derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 pub struct MetricsConfig {
+    /// How often storage usage and I/O are sampled into metrics; defaults to 60s.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report_interval: Option<jiff::SignedDuration>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub opentelemetry: Option<OpentelemetryMetricsConfig>,
 }
 
+impl TelemetryConfig {
+    /// Resolve how often storage usage and I/O are sampled into metrics.
+    pub fn report_interval(&self) -> Result<Duration, ConfigError> {
+        let Some(value) = self.metrics.as_ref().and_then(|m| m.report_interval) else {
+            return Ok(Duration::from_secs(60));
+        };
+        let make_error =
+            || ConfigError("telemetry.metrics.report_interval must be positive".to_string());
+        let value = Duration::try_from(value).or_raise(make_error)?;
+        ensure!(!value.is_zero(), make_error());
+        Ok(value)
+    }
+}
+
+/// Options shared by the OTLP exporters of logs, traces and metrics.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct OtlpConfig {
+    /// With `http/protobuf`, each `otlp_endpoint` is the full URL of its signal, e.g.
+    /// `https://collector:4318/v1/traces`.
+    #[serde(default)]
+    pub protocol: OtlpProtocol,
+    /// Headers sent with every export request, e.g. for authentication.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// The timeout of a single export request; defaults to 10s.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<jiff::SignedDuration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<OtlpCompression>,
+}
+
+impl OtlpConfig {
+    /// Resolve the timeout of a single export request.
+    pub fn timeout(&self) -> Result<Duration, ConfigError> {
+        let Some(value) = self.timeout else {
+            return Ok(Duration::from_secs(10));
+        };
+        let make_error = || ConfigError("telemetry.otlp.timeout must be positive".to_string());
+        let value = Duration::try_from(value).or_raise(make_error)?;
+        ensure!(!value.is_zero(), make_error());
+        Ok(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+pub enum OtlpProtocol {
+    #[default]
+    #[serde(rename = "grpc")]
+    Grpc,
+    #[serde(rename = "http/protobuf")]
+    HttpProtobuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum OtlpCompression {
+    Gzip,
+    Zstd,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[cfg_attr(test, derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
@@ -197,12 +660,18 @@ impl Default for Config {
                 advertise_ctrl_addr: None,
                 initial_peers: Vec::new(),
                 cluster_id: default_cluster_id(),
+                node_id: None,
+                gossip: GossipConfig::default(),
             },
             storage: StorageConfig {
+                engine: StorageEngine::default(),
                 data_dir: default_data_dir(),
                 disk_capacity: default_disk_capacity(),
                 memory_capacity: default_memory_capacity(),
                 disk_throttle: None,
+                io_engine: IoEngineConfig::default(),
+                devices: vec![],
+                faults: None,
             },
             telemetry: TelemetryConfig {
                 logs: LogsConfig {
@@ -225,7 +694,12 @@ impl Default for Config {
                         otlp_endpoint: "http://127.0.0.1:4317".to_string(),
                     }),
                 }),
+                access_log: None,
+                diagnostics: DiagnosticsConfig::default(),
+                otlp: OtlpConfig::default(),
+                resource: BTreeMap::new(),
                 metrics: Some(MetricsConfig {
+                    report_interval: None,
                     opentelemetry: Some(OpentelemetryMetricsConfig {
                         otlp_endpoint: "http://127.0.0.1:4317".to_string(),
                         push_interval: default_metrics_push_interval(),
@@ -236,6 +710,32 @@ impl Default for Config {
     }
 }
 
+impl Config {
+    /// Validate constraints that cannot be expressed by the config types alone.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.server.gossip.timing()?;
+        self.telemetry.report_interval()?;
+        self.telemetry.diagnostics.slow_request_threshold()?;
+        self.telemetry.otlp.timeout()?;
+        ensure!(
+            self.storage.io_engine.io_depth > 0,
+            ConfigError("storage.io_engine.io_depth must be greater than 0".to_string())
+        );
+
+        let devices = &self.storage.devices;
+        for (i, device) in devices.iter().enumerate() {
+            ensure!(
+                devices[..i].iter().all(|d| d.path != device.path),
+                ConfigError(format!(
+                    "storage.devices has duplicate path: {}",
+                    device.path.display()
+                ))
+            );
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
 pub struct OptionEntry {
     /// The name of the environment variable.
@@ -268,6 +768,46 @@ pub const fn known_option_entries() -> &'static [OptionEntry] {
             ent_path: "server.dir",
             ent_type: "string",
         },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_SERVER_GOSSIP_MEMBER_DEADLINE",
+            ent_path: "server.gossip.member_deadline",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_SERVER_GOSSIP_PING_INTERVAL",
+            ent_path: "server.gossip.ping_interval",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_SERVER_GOSSIP_PRESET",
+            ent_path: "server.gossip.preset",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_SERVER_GOSSIP_REBUILD_RING_INTERVAL",
+            ent_path: "server.gossip.rebuild_ring_interval",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_SERVER_GOSSIP_REQUEST_TIMEOUT",
+            ent_path: "server.gossip.request_timeout",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_SERVER_GOSSIP_RETRIES",
+            ent_path: "server.gossip.retries",
+            ent_type: "integer",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_SERVER_GOSSIP_RETRY_INTERVAL",
+            ent_path: "server.gossip.retry_interval",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_SERVER_GOSSIP_SYNC_INTERVAL",
+            ent_path: "server.gossip.sync_interval",
+            ent_type: "string",
+        },
         OptionEntry {
             env_name: "PERCAS_CONFIG_SERVER_INITIAL_PEERS",
             ent_path: "server.initial_peers",
@@ -283,11 +823,21 @@ pub const fn known_option_entries() -> &'static [OptionEntry] {
             ent_path: "server.listen_data_addr",
             ent_type: "string",
         },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_SERVER_NODE_ID",
+            ent_path: "server.node_id",
+            ent_type: "string",
+        },
         OptionEntry {
             env_name: "PERCAS_CONFIG_STORAGE_DATA_DIR",
             ent_path: "storage.data_dir",
             ent_type: "string",
         },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_STORAGE_DEVICES",
+            ent_path: "storage.devices",
+            ent_type: "array",
+        },
         OptionEntry {
             env_name: "PERCAS_CONFIG_STORAGE_DISK_CAPACITY",
             ent_path: "storage.disk_capacity",
@@ -323,11 +873,81 @@ pub const fn known_option_entries() -> &'static [OptionEntry] {
             ent_path: "storage.disk_throttle.write_throughput",
             ent_type: "integer",
         },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_STORAGE_ENGINE",
+            ent_path: "storage.engine",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_STORAGE_FAULTS_ENGINE",
+            ent_path: "storage.faults.engine",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_STORAGE_FAULTS_ERROR_ONE_IN",
+            ent_path: "storage.faults.error_one_in",
+            ent_type: "integer",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_STORAGE_FAULTS_LATENCY",
+            ent_path: "storage.faults.latency",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_STORAGE_IO_ENGINE_DIRECT",
+            ent_path: "storage.io_engine.direct",
+            ent_type: "boolean",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_STORAGE_IO_ENGINE_IO_DEPTH",
+            ent_path: "storage.io_engine.io_depth",
+            ent_type: "integer",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_STORAGE_IO_ENGINE_KIND",
+            ent_path: "storage.io_engine.kind",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_STORAGE_IO_ENGINE_SQPOLL",
+            ent_path: "storage.io_engine.sqpoll",
+            ent_type: "boolean",
+        },
         OptionEntry {
             env_name: "PERCAS_CONFIG_STORAGE_MEMORY_CAPACITY",
             ent_path: "storage.memory_capacity",
             ent_type: "string",
         },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_TELEMETRY_ACCESS_LOG_DIR",
+            ent_path: "telemetry.access_log.dir",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_TELEMETRY_ACCESS_LOG_MAX_FILES",
+            ent_path: "telemetry.access_log.max_files",
+            ent_type: "integer",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_TELEMETRY_ACCESS_LOG_SAMPLE_ONE_IN",
+            ent_path: "telemetry.access_log.sample_one_in",
+            ent_type: "integer",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_TELEMETRY_DIAGNOSTICS_HASH_KEYS",
+            ent_path: "telemetry.diagnostics.hash_keys",
+            ent_type: "boolean",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_TELEMETRY_DIAGNOSTICS_HOT_KEYS",
+            ent_path: "telemetry.diagnostics.hot_keys",
+            ent_type: "integer",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_TELEMETRY_DIAGNOSTICS_SLOW_REQUEST_THRESHOLD",
+            ent_path: "telemetry.diagnostics.slow_request_threshold",
+            ent_type: "string",
+        },
         OptionEntry {
             env_name: "PERCAS_CONFIG_TELEMETRY_LOGS_FILE_DIR",
             ent_path: "telemetry.logs.file.dir",
@@ -368,6 +988,36 @@ pub const fn known_option_entries() -> &'static [OptionEntry] {
             ent_path: "telemetry.metrics.opentelemetry.push_interval",
             ent_type: "string",
         },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_TELEMETRY_METRICS_REPORT_INTERVAL",
+            ent_path: "telemetry.metrics.report_interval",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_TELEMETRY_OTLP_COMPRESSION",
+            ent_path: "telemetry.otlp.compression",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_TELEMETRY_OTLP_HEADERS",
+            ent_path: "telemetry.otlp.headers",
+            ent_type: "object",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_TELEMETRY_OTLP_PROTOCOL",
+            ent_path: "telemetry.otlp.protocol",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_TELEMETRY_OTLP_TIMEOUT",
+            ent_path: "telemetry.otlp.timeout",
+            ent_type: "string",
+        },
+        OptionEntry {
+            env_name: "PERCAS_CONFIG_TELEMETRY_RESOURCE",
+            ent_path: "telemetry.resource",
+            ent_type: "object",
+        },
         OptionEntry {
             env_name: "PERCAS_CONFIG_TELEMETRY_TRACES_CAPTURE_LOG_FILTER",
             ent_path: "telemetry.traces.capture_log_filter",
@@ -474,7 +1124,7 @@ mod codegen {
             let ty = ty.as_str().unwrap();
             match ty {
                 "null" => {}
-                "object" => {
+                "object" if o.contains_key("properties") => {
                     let props = o.get("properties").unwrap().as_object().unwrap();
                     for (k, v) in props {
                         let prefix = if prefix.is_empty() {
@@ -525,11 +1175,19 @@ mod tests {
             listen_data_addr = '0.0.0.0:7654'
             listen_ctrl_addr = '0.0.0.0:7655'
             cluster_id = 'percas-cluster'
+            [server.gossip]
+            preset = 'lan'
 
             [storage]
+            engine = 'foyer'
             data_dir = '/var/lib/percas/data'
             disk_capacity = '512.0 MiB'
             memory_capacity = '[available memory size]'
+            [storage.io_engine]
+            kind = 'io_uring'
+            sqpoll = true
+            io_depth = 64
+            direct = false
             [telemetry.logs.file]
             filter = 'INFO'
             dir = 'logs'
@@ -546,7 +1204,45 @@ mod tests {
             [telemetry.metrics.opentelemetry]
             otlp_endpoint = 'http://127.0.0.1:4317'
             push_interval = 'PT30S'
+            [telemetry.diagnostics]
+            hot_keys = 32
+            slow_request_threshold = 'PT0.1S'
+            hash_keys = false
+            [telemetry.otlp]
+            protocol = 'grpc'
             "
         );
     }
+
+    #[test]
+    fn gossip_timing() {
+        let config = GossipConfig {
+            preset: GossipPreset::Wan,
+            ping_interval: Some(jiff::SignedDuration::from_secs(3)),
+            ..Default::default()
+        };
+        let timing = config.timing().unwrap();
+        assert_eq!(timing.ping_interval, Duration::from_secs(3));
+        assert_eq!(timing.retries, GossipPreset::Wan.timing().retries);
+
+        let config = GossipConfig {
+            member_deadline: Some(jiff::SignedDuration::from_secs(2)),
+            ..Default::default()
+        };
+        assert!(config.timing().is_err());
+
+        let config = GossipConfig {
+            sync_interval: Some(jiff::SignedDuration::from_secs(-1)),
+            ..Default::default()
+        };
+        assert!(config.timing().is_err());
+
+        for preset in [GossipPreset::Dev, GossipPreset::Lan, GossipPreset::Wan] {
+            let config = GossipConfig {
+                preset,
+                ..Default::default()
+            };
+            assert_eq!(config.timing().unwrap(), preset.timing());
+        }
+    }
 }
@@ -0,0 +1,203 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Raw block devices as data devices of the disk engine.
+
+use std::fs::File;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::path::Path;
+
+use bytesize::ByteSize;
+use exn::Result;
+use exn::ResultExt;
+use exn::bail;
+
+use super::EngineError;
+
+/// How much of the head of a device is inspected for signatures; covers the btrfs superblock.
+const PROBE_SIZE: usize = 0x10048;
+
+/// A well-known on-disk signature: a magic value at a fixed offset.
+struct Signature {
+    name: &'static str,
+    offset: usize,
+    magic: &'static [u8],
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature {
+        name: "xfs",
+        offset: 0,
+        magic: b"XFSB",
+    },
+    Signature {
+        name: "luks",
+        offset: 0,
+        magic: b"LUKS\xba\xbe",
+    },
+    Signature {
+        name: "ntfs",
+        offset: 3,
+        magic: b"NTFS    ",
+    },
+    Signature {
+        name: "vfat",
+        offset: 54,
+        magic: b"FAT16   ",
+    },
+    Signature {
+        name: "vfat",
+        offset: 82,
+        magic: b"FAT32   ",
+    },
+    Signature {
+        name: "gpt",
+        offset: 512,
+        magic: b"EFI PART",
+    },
+    Signature {
+        name: "swap",
+        offset: 4086,
+        magic: b"SWAPSPACE2",
+    },
+    Signature {
+        name: "btrfs",
+        offset: 0x10040,
+        magic: b"_BHRfS_M",
+    },
+];
+
+/// Detect a filesystem or partition table signature at the head of a device.
+pub(crate) fn detect_signature(head: &[u8]) -> Option<&'static str> {
+    let found = SIGNATURES
+        .iter()
+        .find(|sig| head.get(sig.offset..sig.offset + sig.magic.len()) == Some(sig.magic));
+    if let Some(sig) = found {
+        return Some(sig.name);
+    }
+
+    // ext2/3/4 has a two byte magic, so also check fields of the superblock to avoid false hits
+    const EXT_SUPERBLOCK: usize = 1024;
+    let read_u32 = |offset: usize| {
+        head.get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    };
+    let magic = head.get(EXT_SUPERBLOCK + 56..EXT_SUPERBLOCK + 58);
+    let log_block_size = read_u32(EXT_SUPERBLOCK + 24);
+    let rev_level = read_u32(EXT_SUPERBLOCK + 76);
+    if magic == Some(&[0x53, 0xef])
+        && log_block_size.is_some_and(|v| v <= 6)
+        && rev_level.is_some_and(|v| v <= 1)
+    {
+        return Some("ext");
+    }
+
+    None
+}
+
+/// Open a raw block device and return the capacity to use on it.
+///
+/// The capacity defaults to the size of the device. Devices that carry a filesystem or a
+/// partition table are refused unless `force` is set.
+pub(crate) fn probe_block_device(
+    path: &Path,
+    capacity: Option<ByteSize>,
+    force: bool,
+) -> Result<ByteSize, EngineError> {
+    let make_error = || EngineError(format!("failed to open block device {}", path.display()));
+
+    let mut file = File::open(path).or_raise(make_error)?;
+    let metadata = file.metadata().or_raise(make_error)?;
+    if !is_block_device(&metadata) {
+        bail!(EngineError(format!(
+            "{} is not a block device; use a device of kind \"fs\" for files and directories",
+            path.display()
+        )));
+    }
+
+    // the size of a block device is only visible by seeking to its end
+    let size = ByteSize(file.seek(SeekFrom::End(0)).or_raise(make_error)?);
+    file.seek(SeekFrom::Start(0)).or_raise(make_error)?;
+
+    let mut head = vec![0; PROBE_SIZE.min(size.0 as usize)];
+    file.read_exact(&mut head).or_raise(make_error)?;
+    if let Some(signature) = detect_signature(&head) {
+        if !force {
+            bail!(EngineError(format!(
+                "block device {} carries a {signature} signature; set force = true to overwrite it",
+                path.display()
+            )));
+        }
+        log::warn!(
+            "overwriting {signature} signature on block device {} as forced",
+            path.display()
+        );
+    }
+
+    match capacity {
+        None => Ok(size),
+        Some(capacity) if capacity <= size => Ok(capacity),
+        Some(capacity) => bail!(EngineError(format!(
+            "capacity {capacity} exceeds the size {size} of block device {}",
+            path.display()
+        ))),
+    }
+}
+
+#[cfg(unix)]
+fn is_block_device(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    metadata.file_type().is_block_device()
+}
+
+#[cfg(not(unix))]
+fn is_block_device(_: &std::fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_signature() {
+        let mut head = vec![0; PROBE_SIZE];
+        assert_eq!(detect_signature(&head), None);
+
+        head[0x10040..0x10048].copy_from_slice(b"_BHRfS_M");
+        assert_eq!(detect_signature(&head), Some("btrfs"));
+
+        let mut head = vec![0; PROBE_SIZE];
+        head[1024 + 56..1024 + 58].copy_from_slice(&[0x53, 0xef]);
+        head[1024 + 24..1024 + 28].copy_from_slice(&2u32.to_le_bytes());
+        head[1024 + 76..1024 + 80].copy_from_slice(&1u32.to_le_bytes());
+        assert_eq!(detect_signature(&head), Some("ext"));
+
+        // the ext magic alone is not enough
+        head[1024 + 76..1024 + 80].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(detect_signature(&head), None);
+
+        // short devices are probed as far as they go
+        assert_eq!(detect_signature(b"XFSB"), Some("xfs"));
+    }
+
+    #[test]
+    fn test_refuse_regular_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let err = probe_block_device(file.path(), None, false).unwrap_err();
+        assert!(err.to_string().contains("is not a block device"), "{err}");
+    }
+}
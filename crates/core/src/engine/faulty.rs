@@ -0,0 +1,184 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use exn::Result;
+use exn::bail;
+use fastimer::MakeDelay;
+use futures_util::FutureExt;
+use futures_util::future::BoxFuture;
+use rand::Rng;
+
+use super::Engine;
+use super::EngineError;
+use super::EngineStatistics;
+use super::EngineStatus;
+use super::EngineUsage;
+use super::Hit;
+use crate::timer;
+
+/// Faults injected into the operations of a [`FaultyEngine`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Faults {
+    /// The probability in `[0, 1]` that an operation fails.
+    pub error_rate: f64,
+    /// The delay added to every operation.
+    pub latency: Duration,
+}
+
+/// An engine wrapper that injects errors and latency into the wrapped engine, for tests.
+///
+/// It is selected with `[storage] engine = "faulty"`, which wraps the engine named in
+/// `[storage.faults]`. Faults can be changed while the engine is serving requests.
+pub struct FaultyEngine {
+    inner: Arc<dyn Engine>,
+    faults: RwLock<Faults>,
+}
+
+impl FaultyEngine {
+    pub fn new(inner: Arc<dyn Engine>) -> Self {
+        Self {
+            inner,
+            faults: RwLock::new(Faults::default()),
+        }
+    }
+
+    pub fn faults(&self) -> Faults {
+        *self.faults.read().unwrap()
+    }
+
+    pub fn set_faults(&self, faults: Faults) {
+        *self.faults.write().unwrap() = faults;
+    }
+
+    async fn inject(&self, operation: &str) -> Result<(), EngineError> {
+        let faults = self.faults();
+        if !faults.latency.is_zero() {
+            timer().delay(faults.latency).await;
+        }
+        if rand::rng().random_bool(faults.error_rate.clamp(0.0, 1.0)) {
+            bail!(EngineError(format!("injected fault on {operation}")));
+        }
+        Ok(())
+    }
+}
+
+impl Engine for FaultyEngine {
+    fn get<'a>(&'a self, key: &'a [u8]) -> BoxFuture<'a, Result<Option<Hit>, EngineError>> {
+        async move {
+            self.inject("get").await?;
+            self.inner.get(key).await
+        }
+        .boxed()
+    }
+
+    fn put<'a>(&'a self, key: &'a [u8], value: &'a [u8]) -> BoxFuture<'a, Result<(), EngineError>> {
+        async move {
+            self.inject("put").await?;
+            self.inner.put(key, value).await
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, key: &'a [u8]) -> BoxFuture<'a, Result<(), EngineError>> {
+        async move {
+            self.inject("delete").await?;
+            self.inner.delete(key).await
+        }
+        .boxed()
+    }
+
+    fn capacity(&self) -> u64 {
+        self.inner.capacity()
+    }
+
+    fn statistics(&self) -> EngineStatistics {
+        self.inner.statistics()
+    }
+
+    fn usage(&self) -> EngineUsage {
+        self.inner.usage()
+    }
+
+    fn status(&self) -> EngineStatus {
+        self.inner.status()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use bytesize::ByteSize;
+
+    use super::*;
+    use crate::Config;
+    use crate::FaultsConfig;
+    use crate::MemoryEngine;
+    use crate::StorageEngine;
+    use crate::Tier;
+    use crate::open_engine;
+    use crate::runtime;
+
+    #[test]
+    fn test_inject_errors() {
+        let runtime = runtime::make_runtime("test_runtime", "test_thread", 1);
+
+        runtime.block_on(async {
+            let engine = FaultyEngine::new(Arc::new(MemoryEngine::new(ByteSize::mib(1))));
+            engine.put(b"foo", b"bar").await.unwrap();
+
+            engine.set_faults(Faults {
+                error_rate: 1.0,
+                latency: Duration::from_millis(10),
+            });
+            assert!(engine.get(b"foo").await.is_err());
+            assert!(engine.put(b"foo", b"baz").await.is_err());
+            assert!(engine.delete(b"foo").await.is_err());
+
+            engine.set_faults(Faults::default());
+            assert_eq!(
+                engine.get(b"foo").await.unwrap(),
+                Some(Hit {
+                    value: b"bar".to_vec(),
+                    tier: Tier::Memory,
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn test_open_from_config() {
+        let runtime = runtime::make_runtime("test_runtime", "test_thread", 1);
+
+        runtime.block_on(async {
+            let mut config = Config::default().storage;
+            config.engine = StorageEngine::Faulty;
+            config.faults = Some(FaultsConfig {
+                engine: StorageEngine::Memory,
+                error_one_in: NonZeroU32::new(1),
+                latency: jiff::SignedDuration::ZERO,
+            });
+            let engine = open_engine(&runtime, &config, None).await.unwrap();
+            assert_eq!(engine.status().engine, StorageEngine::Memory);
+            assert!(engine.put(b"foo", b"bar").await.is_err());
+
+            config.faults.as_mut().unwrap().engine = StorageEngine::Faulty;
+            assert!(open_engine(&runtime, &config, None).await.is_err());
+        });
+    }
+}
@@ -0,0 +1,425 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use bytesize::ByteSize;
+use exn::Result;
+use exn::bail;
+use fastrace::Span;
+use fastrace::future::FutureExt as _;
+use foyer::BlockEngineConfig;
+use foyer::DeviceBuilder;
+use foyer::FileDeviceBuilder;
+use foyer::FsDeviceBuilder;
+use foyer::HybridCache;
+use foyer::HybridCacheBuilder;
+use foyer::HybridCachePolicy;
+use foyer::IopsCounter;
+use foyer::LfuConfig;
+use foyer::PsyncIoEngineConfig;
+use foyer::RecoverMode;
+use foyer::Source;
+use foyer::Spawner;
+use futures_util::FutureExt;
+use futures_util::future::BoxFuture;
+use mixtrics::registry::noop::NoopMetricsRegistry;
+use mixtrics::registry::opentelemetry_0_31::OpenTelemetryMetricsRegistry;
+
+use super::DEFAULT_MEMORY_CAPACITY_FACTOR;
+use super::Engine;
+use super::EngineError;
+use super::EngineStatistics;
+use super::EngineStatus;
+use super::EngineUsage;
+use super::Hit;
+use super::IoEngineStatus;
+use super::Tier;
+use super::block::probe_block_device;
+use super::usage::EvictionListener;
+use super::usage::UsageRegistry;
+use super::usage::UsageTracker;
+use crate::DeviceConfig;
+use crate::DeviceKind;
+use crate::IoEngineConfig;
+use crate::IoEngineKind;
+use crate::StorageEngine;
+use crate::default_disk_capacity;
+use crate::num_cpus;
+use crate::runtime;
+
+const DEFAULT_BLOCK_SIZE: ByteSize = ByteSize::mib(64);
+const DEFAULT_FLUSHERS: usize = 4; // Number of flushers for the block engine
+#[cfg(target_os = "linux")]
+const SQPOLL_IDLE_MILLIS: u32 = 10; // Same as the foyer default
+
+/// A hybrid engine that keeps hot entries in memory and spills the rest to disk.
+pub struct FoyerEngine {
+    inner: HybridCache<Vec<u8>, Vec<u8>>,
+    capacity: ByteSize,
+    io_engine: IoEngineStatus,
+    usage: Arc<UsageTracker>,
+}
+
+impl FoyerEngine {
+    pub async fn try_new(
+        io_runtime: &runtime::Runtime,
+        name: &str,
+        device: &DeviceConfig,
+        memory_capacity: ByteSize,
+        io_engine: &IoEngineConfig,
+        metrics_registry: Option<OpenTelemetryMetricsRegistry>,
+    ) -> Result<Self, EngineError> {
+        let throttle = match device.throttle.clone() {
+            Some(throttle) => throttle.into(),
+            None => default_throttle(),
+        };
+        let (dev, disk_capacity) = match device.kind {
+            DeviceKind::Fs => {
+                let data_dir = device.path.as_path();
+                let disk_capacity: ByteSize = device
+                    .capacity
+                    .clone()
+                    .unwrap_or_else(default_disk_capacity)
+                    .into();
+                let _ = std::fs::create_dir_all(data_dir);
+                if !data_dir.exists() {
+                    bail!(EngineError(format!(
+                        "failed to create data dir: {}",
+                        data_dir.display()
+                    )));
+                }
+
+                let db = FsDeviceBuilder::new(data_dir)
+                    .with_capacity(disk_capacity.0 as usize)
+                    .with_throttle(throttle);
+                #[cfg(target_os = "linux")]
+                let db = db.with_direct(io_engine.direct);
+                (db.build(), disk_capacity)
+            }
+            DeviceKind::Block => {
+                let capacity = device.capacity.clone().map(Into::into);
+                let disk_capacity = probe_block_device(&device.path, capacity, device.force)?;
+                log::info!(
+                    "using block device {} with capacity {disk_capacity}",
+                    device.path.display()
+                );
+
+                let db = FileDeviceBuilder::new(&device.path)
+                    .with_capacity(disk_capacity.0 as usize)
+                    .with_throttle(throttle);
+                #[cfg(target_os = "linux")]
+                let db = db.with_direct(io_engine.direct);
+                (db.build(), disk_capacity)
+            }
+        };
+        let dev = dev.map_err(|err| EngineError(format!("failed to create device: {err}")))?;
+
+        let io_engine = select_io_engine(io_engine);
+        log::info!("storage engine selected io engine: {io_engine:?}");
+
+        let parallelism = num_cpus().get();
+        let usage = Arc::new(UsageTracker::default());
+        let cache = HybridCacheBuilder::new()
+            .with_name(name.to_string())
+            .with_policy(HybridCachePolicy::WriteOnEviction)
+            .with_event_listener(Arc::new(EvictionListener::new(usage.clone())))
+            .with_metrics_registry(Box::new(UsageRegistry::new(
+                match metrics_registry {
+                    Some(registry) => Box::new(registry),
+                    None => Box::new(NoopMetricsRegistry),
+                },
+                usage.clone(),
+            )))
+            .memory((memory_capacity.0 as f64 * DEFAULT_MEMORY_CAPACITY_FACTOR) as usize)
+            .with_weighter(|key: &Vec<u8>, value: &Vec<u8>| {
+                let key_size = key.len();
+                let value_size = value.len();
+                key_size + value_size
+            })
+            .with_shards(parallelism.max(32))
+            .with_eviction_config(LfuConfig::default())
+            .storage()
+            .with_engine_config(
+                BlockEngineConfig::new(dev)
+                    .with_recover_concurrency(parallelism)
+                    .with_block_size(DEFAULT_BLOCK_SIZE.0 as usize)
+                    .with_flushers(DEFAULT_FLUSHERS),
+            )
+            .with_io_engine_config(make_io_engine_config(&io_engine))
+            .with_recover_mode(RecoverMode::Quiet)
+            .with_spawner(io_runtime.spawn_blocking(Spawner::current).await)
+            .build()
+            .await
+            .map_err(|err| EngineError(err.to_string()))?;
+
+        Ok(FoyerEngine {
+            inner: cache,
+            capacity: disk_capacity,
+            io_engine,
+            usage,
+        })
+    }
+}
+
+impl Engine for FoyerEngine {
+    fn get<'a>(&'a self, key: &'a [u8]) -> BoxFuture<'a, Result<Option<Hit>, EngineError>> {
+        async move {
+            let get = self.inner.get(&key.to_owned());
+            // A get that cannot complete from memory waits on the disk read, or on another
+            // in-flight read of the same key.
+            let result = if get.need_await() {
+                get.in_span(Span::enter_with_local_parent("engine.disk_read"))
+                    .await
+            } else {
+                get.await
+            };
+            match result {
+                Ok(entry) => Ok(entry.map(|entry| Hit {
+                    value: entry.value().clone(),
+                    tier: match entry.source() {
+                        Source::Memory => Tier::Memory,
+                        Source::Disk | Source::Outer => Tier::Disk,
+                    },
+                })),
+                Err(err) => bail!(EngineError(format!("failed to get value: {err}"))),
+            }
+        }
+        .boxed()
+    }
+
+    fn put<'a>(&'a self, key: &'a [u8], value: &'a [u8]) -> BoxFuture<'a, Result<(), EngineError>> {
+        self.inner.insert(key.to_owned(), value.to_owned());
+        futures_util::future::ok(()).boxed()
+    }
+
+    fn delete<'a>(&'a self, key: &'a [u8]) -> BoxFuture<'a, Result<(), EngineError>> {
+        self.inner.remove(key);
+        futures_util::future::ok(()).boxed()
+    }
+
+    fn capacity(&self) -> u64 {
+        self.capacity.as_u64()
+    }
+
+    fn statistics(&self) -> EngineStatistics {
+        let stats = self.inner.statistics();
+        EngineStatistics {
+            disk_read_bytes: stats.disk_read_bytes() as _,
+            disk_write_bytes: stats.disk_write_bytes() as _,
+            disk_read_ios: stats.disk_read_ios() as _,
+            disk_write_ios: stats.disk_write_ios() as _,
+            flush_dropped_entries: self.usage.flush_dropped_entries(),
+            memory_evictions: self.usage.memory_evictions(),
+            disk_reclaimed_blocks: self.usage.disk_reclaimed_blocks(),
+        }
+    }
+
+    fn usage(&self) -> EngineUsage {
+        let memory = self.inner.memory();
+        let disk_blocks = self.usage.blocks();
+        EngineUsage {
+            memory_used_bytes: memory.usage() as u64,
+            memory_capacity_bytes: memory.capacity() as u64,
+            memory_entries: memory.entries() as u64,
+            disk_allocated_bytes: (disk_blocks.total() - disk_blocks.clean)
+                * self.usage.block_size(),
+            disk_capacity_bytes: self.capacity(),
+            disk_blocks,
+        }
+    }
+
+    fn status(&self) -> EngineStatus {
+        EngineStatus {
+            engine: StorageEngine::Foyer,
+            capacity: self.capacity(),
+            io_engine: Some(self.io_engine),
+            devices: vec![],
+        }
+    }
+}
+
+/// Resolve the I/O engine to run on, falling back when io_uring cannot be set up on this host.
+fn select_io_engine(config: &IoEngineConfig) -> IoEngineStatus {
+    let psync = IoEngineStatus {
+        kind: IoEngineKind::Psync,
+        sqpoll: false,
+        io_depth: config.io_depth,
+        direct: config.direct && cfg!(target_os = "linux"),
+    };
+    if config.direct && !psync.direct {
+        log::warn!("direct I/O is only available on linux (fallback to buffered I/O)");
+    }
+
+    match config.kind {
+        IoEngineKind::Psync => psync,
+        #[cfg(target_os = "linux")]
+        IoEngineKind::IoUring => {
+            let uring = IoEngineStatus {
+                kind: IoEngineKind::IoUring,
+                sqpoll: config.sqpoll,
+                io_depth: config.io_depth,
+                direct: psync.direct,
+            };
+
+            if uring.sqpoll {
+                match probe_io_uring(uring.io_depth, true) {
+                    Ok(()) => return uring,
+                    Err(err) => log::warn!(
+                        "failed to set up io_uring with sqpoll (fallback to no sqpoll): {err}"
+                    ),
+                }
+            }
+
+            match probe_io_uring(uring.io_depth, false) {
+                Ok(()) => IoEngineStatus {
+                    sqpoll: false,
+                    ..uring
+                },
+                Err(err) => {
+                    log::warn!("failed to set up io_uring (fallback to psync): {err}");
+                    psync
+                }
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        IoEngineKind::IoUring => {
+            log::warn!("io_uring is only available on linux (fallback to psync)");
+            psync
+        }
+    }
+}
+
+/// Set up and tear down a ring the way foyer does, so that failures surface before the cache is
+/// built.
+#[cfg(target_os = "linux")]
+fn probe_io_uring(io_depth: usize, sqpoll: bool) -> std::io::Result<()> {
+    let mut builder = io_uring::IoUring::<io_uring::squeue::Entry>::builder();
+    if sqpoll {
+        builder.setup_sqpoll(SQPOLL_IDLE_MILLIS);
+    }
+    let io_depth = u32::try_from(io_depth).map_err(std::io::Error::other)?;
+    builder.build(io_depth).map(|_| ())
+}
+
+fn make_io_engine_config(status: &IoEngineStatus) -> Box<dyn foyer::IoEngineConfig> {
+    match status.kind {
+        #[cfg(target_os = "linux")]
+        IoEngineKind::IoUring => Box::new(
+            foyer::UringIoEngineConfig::new()
+                .with_sqpoll(status.sqpoll)
+                .with_sqpoll_idle(SQPOLL_IDLE_MILLIS)
+                .with_io_depth(status.io_depth),
+        ),
+        _ => Box::new(PsyncIoEngineConfig::new()),
+    }
+}
+
+/// Throttle a device to a share of the expected throughput and IOPS of the node.
+fn default_throttle() -> foyer::Throttle {
+    const DEFAULT_THROUGHPUT_PER_CORE: usize = 187_500_000; // ~1.5Gbps
+    const IOPS_PER_CORE: usize = 10_000; // 10k IOPS
+    let throughput = DEFAULT_THROUGHPUT_PER_CORE * num_cpus().get();
+    let write_throughput_quota = throughput / 4; // 25% of throughput for writes
+    let read_throughput_quota = throughput - write_throughput_quota; // Remaining for reads
+    let iops = IOPS_PER_CORE * num_cpus().get();
+    let write_iops_quota = iops / 4; // 25% of IOPS for writes
+    let read_iops_quota = iops - write_iops_quota; // Remaining for reads
+    foyer::Throttle {
+        write_iops: Some(write_iops_quota.try_into().unwrap()),
+        read_iops: Some(read_iops_quota.try_into().unwrap()),
+        write_throughput: Some(write_throughput_quota.try_into().unwrap()),
+        read_throughput: Some(read_throughput_quota.try_into().unwrap()),
+        iops_counter: IopsCounter::PerIo,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_compact_debug_snapshot;
+
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        let runtime = runtime::make_runtime("test_runtime", "test_thread", 2);
+
+        runtime.block_on(async {
+            let temp_dir = tempfile::tempdir().unwrap();
+
+            let device = DeviceConfig {
+                kind: DeviceKind::Fs,
+                path: temp_dir.path().to_path_buf(),
+                capacity: Some(ByteSize::mib(1).into()),
+                throttle: None,
+                force: false,
+            };
+            let engine = FoyerEngine::try_new(
+                &runtime,
+                "foyer",
+                &device,
+                ByteSize::kib(512),
+                &IoEngineConfig::default(),
+                None,
+            )
+            .await
+            .unwrap();
+
+            engine.put(b"foo", b"bar").await.unwrap();
+
+            assert_compact_debug_snapshot!(
+                engine.get(b"foo").await.unwrap(),
+                @"Some(Hit { value: [98, 97, 114], tier: Memory })"
+            );
+        });
+    }
+
+    #[test]
+    fn test_usage() {
+        let runtime = runtime::make_runtime("test_runtime", "test_thread", 2);
+
+        runtime.block_on(async {
+            let temp_dir = tempfile::tempdir().unwrap();
+
+            let device = DeviceConfig {
+                kind: DeviceKind::Fs,
+                path: temp_dir.path().to_path_buf(),
+                capacity: Some(ByteSize::mib(256).into()),
+                throttle: None,
+                force: false,
+            };
+            let engine = FoyerEngine::try_new(
+                &runtime,
+                "foyer",
+                &device,
+                ByteSize::kib(512),
+                &IoEngineConfig::default(),
+                None,
+            )
+            .await
+            .unwrap();
+
+            let usage = engine.usage();
+            assert_eq!(usage.memory_entries, 0);
+            assert_eq!(usage.disk_capacity_bytes, ByteSize::mib(256).as_u64());
+            assert_eq!(usage.disk_blocks.total(), 4);
+
+            engine.put(b"foo", b"bar").await.unwrap();
+            let usage = engine.usage();
+            assert_eq!(usage.memory_entries, 1);
+            assert_eq!(usage.memory_used_bytes, 6);
+            assert_eq!(usage.memory_capacity_bytes, ByteSize::kib(256).as_u64());
+        });
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use bytesize::ByteSize;
+use exn::Result;
+use foyer::Cache;
+use foyer::CacheBuilder;
+use foyer::LfuConfig;
+use futures_util::FutureExt;
+use futures_util::future::BoxFuture;
+
+use super::DEFAULT_MEMORY_CAPACITY_FACTOR;
+use super::Engine;
+use super::EngineError;
+use super::EngineStatistics;
+use super::EngineStatus;
+use super::EngineUsage;
+use super::Hit;
+use super::Tier;
+use super::usage::EvictionListener;
+use super::usage::UsageTracker;
+use crate::StorageEngine;
+use crate::num_cpus;
+
+/// An engine that keeps every entry in memory and never touches the disk.
+///
+/// Entries are evicted once the memory capacity is exhausted. It suits tests and hosts where the
+/// disk engine cannot run, e.g. without io_uring.
+pub struct MemoryEngine {
+    inner: Cache<Vec<u8>, Vec<u8>>,
+    capacity: ByteSize,
+    tracker: Arc<UsageTracker>,
+}
+
+impl MemoryEngine {
+    pub fn new(memory_capacity: ByteSize) -> Self {
+        let capacity = (memory_capacity.0 as f64 * DEFAULT_MEMORY_CAPACITY_FACTOR) as usize;
+        let tracker = Arc::new(UsageTracker::default());
+        let inner = CacheBuilder::new(capacity)
+            .with_weighter(|key: &Vec<u8>, value: &Vec<u8>| key.len() + value.len())
+            .with_shards(num_cpus().get().max(32))
+            .with_eviction_config(LfuConfig::default())
+            .with_event_listener(Arc::new(EvictionListener::new(tracker.clone())))
+            .build();
+
+        MemoryEngine {
+            inner,
+            capacity: ByteSize(capacity as u64),
+            tracker,
+        }
+    }
+}
+
+impl Engine for MemoryEngine {
+    fn get<'a>(&'a self, key: &'a [u8]) -> BoxFuture<'a, Result<Option<Hit>, EngineError>> {
+        let hit = self.inner.get(key).map(|entry| Hit {
+            value: entry.value().clone(),
+            tier: Tier::Memory,
+        });
+        futures_util::future::ok(hit).boxed()
+    }
+
+    fn put<'a>(&'a self, key: &'a [u8], value: &'a [u8]) -> BoxFuture<'a, Result<(), EngineError>> {
+        self.inner.insert(key.to_owned(), value.to_owned());
+        futures_util::future::ok(()).boxed()
+    }
+
+    fn delete<'a>(&'a self, key: &'a [u8]) -> BoxFuture<'a, Result<(), EngineError>> {
+        self.inner.remove(key);
+        futures_util::future::ok(()).boxed()
+    }
+
+    fn capacity(&self) -> u64 {
+        self.capacity.as_u64()
+    }
+
+    fn statistics(&self) -> EngineStatistics {
+        EngineStatistics {
+            memory_evictions: self.tracker.memory_evictions(),
+            ..EngineStatistics::default()
+        }
+    }
+
+    fn usage(&self) -> EngineUsage {
+        EngineUsage {
+            memory_used_bytes: self.inner.usage() as u64,
+            memory_capacity_bytes: self.inner.capacity() as u64,
+            memory_entries: self.inner.entries() as u64,
+            ..EngineUsage::default()
+        }
+    }
+
+    fn status(&self) -> EngineStatus {
+        EngineStatus {
+            engine: StorageEngine::Memory,
+            capacity: self.capacity(),
+            io_engine: None,
+            devices: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime;
+
+    #[test]
+    fn test_get_put_delete() {
+        let runtime = runtime::make_runtime("test_runtime", "test_thread", 1);
+
+        runtime.block_on(async {
+            let engine = MemoryEngine::new(ByteSize::mib(1));
+
+            engine.put(b"foo", b"bar").await.unwrap();
+            assert_eq!(
+                engine.get(b"foo").await.unwrap(),
+                Some(Hit {
+                    value: b"bar".to_vec(),
+                    tier: Tier::Memory,
+                })
+            );
+
+            engine.delete(b"foo").await.unwrap();
+            assert_eq!(engine.get(b"foo").await.unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn test_evict_beyond_capacity() {
+        let runtime = runtime::make_runtime("test_runtime", "test_thread", 1);
+
+        runtime.block_on(async {
+            let engine = MemoryEngine::new(ByteSize::kib(512));
+
+            let value = vec![0; 1024];
+            for i in 0..1024 {
+                let key = format!("key-{i}");
+                engine.put(key.as_bytes(), &value).await.unwrap();
+            }
+            assert!(engine.inner.usage() as u64 <= engine.capacity());
+
+            engine.put(b"foo", b"bar").await.unwrap();
+            engine.put(b"foo", b"baz").await.unwrap();
+            engine.delete(b"foo").await.unwrap();
+            let evictions = engine.statistics().memory_evictions;
+            assert!(evictions.evicted > 0);
+            assert_eq!(evictions.replaced, 1);
+            assert_eq!(evictions.removed, 1);
+        });
+    }
+}
@@ -0,0 +1,214 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage engines that back the data plane of a node.
+
+mod block;
+mod faulty;
+mod hybrid;
+mod memory;
+mod sharded;
+mod usage;
+
+use std::sync::Arc;
+
+use bytesize::ByteSize;
+use exn::Result;
+use exn::ResultExt;
+use exn::bail;
+use futures_util::future::BoxFuture;
+use mixtrics::registry::opentelemetry_0_31::OpenTelemetryMetricsRegistry;
+use opentelemetry::metrics::Meter;
+use parse_display::Display;
+use serde::Serialize;
+
+pub use self::faulty::Faults;
+pub use self::faulty::FaultyEngine;
+pub use self::hybrid::FoyerEngine;
+pub use self::memory::MemoryEngine;
+pub use self::sharded::DeviceStatus;
+pub use self::sharded::Shard;
+pub use self::sharded::ShardedEngine;
+pub use self::usage::BlockUsage;
+pub use self::usage::EngineUsage;
+pub use self::usage::MemoryEvictions;
+use crate::IoEngineKind;
+use crate::StorageConfig;
+use crate::StorageEngine;
+use crate::runtime;
+
+const DEFAULT_MEMORY_CAPACITY_FACTOR: f64 = 0.5; // 50% of available memory
+
+#[derive(Debug, Display)]
+pub struct EngineError(String);
+
+impl std::error::Error for EngineError {}
+
+/// Counters of the disk I/O performed by an engine since it was opened.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EngineStatistics {
+    pub disk_read_bytes: u64,
+    pub disk_write_bytes: u64,
+    pub disk_read_ios: u64,
+    pub disk_write_ios: u64,
+    /// Entries dropped on their way to disk because flushing could not keep up.
+    pub flush_dropped_entries: u64,
+    /// Entries that left the memory tier, by reason.
+    pub memory_evictions: MemoryEvictions,
+    /// Disk blocks evicted to make room for new entries.
+    pub disk_reclaimed_blocks: u64,
+}
+
+/// The tier of an engine a value was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+    Memory,
+    Disk,
+}
+
+/// A value found by [`Engine::get`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    pub value: Vec<u8>,
+    pub tier: Tier,
+}
+
+/// What an engine runs on, as resolved when it was opened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EngineStatus {
+    pub engine: StorageEngine,
+    pub capacity: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub io_engine: Option<IoEngineStatus>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<DeviceStatus>,
+}
+
+/// The I/O engine a disk engine actually runs on, after any fallback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct IoEngineStatus {
+    pub kind: IoEngineKind,
+    pub sqpoll: bool,
+    pub io_depth: usize,
+    pub direct: bool,
+}
+
+/// A key-value store that serves the data requests of a node.
+pub trait Engine: Send + Sync + 'static {
+    /// Get a value from the engine by key, along with the tier it was found in.
+    fn get<'a>(&'a self, key: &'a [u8]) -> BoxFuture<'a, Result<Option<Hit>, EngineError>>;
+
+    /// Put a key-value pair into the engine.
+    fn put<'a>(&'a self, key: &'a [u8], value: &'a [u8]) -> BoxFuture<'a, Result<(), EngineError>>;
+
+    /// Delete a key-value pair from the engine by key.
+    fn delete<'a>(&'a self, key: &'a [u8]) -> BoxFuture<'a, Result<(), EngineError>>;
+
+    /// Return the capacity of the engine in bytes.
+    fn capacity(&self) -> u64;
+
+    /// Return the disk I/O statistics of the engine.
+    fn statistics(&self) -> EngineStatistics;
+
+    /// Return how full the engine is.
+    fn usage(&self) -> EngineUsage;
+
+    /// Return what the engine runs on.
+    fn status(&self) -> EngineStatus;
+}
+
+/// Open the engine selected by `[storage] engine`.
+///
+/// The disk engine opens every configured device and shards keys over them. A device that fails
+/// to open is left out with an error log; opening fails only if no device is left.
+///
+/// The faulty engine opens the engine named in `[storage.faults]` and wraps it.
+pub async fn open_engine(
+    io_runtime: &runtime::Runtime,
+    config: &StorageConfig,
+    meter: Option<Meter>,
+) -> Result<Arc<dyn Engine>, EngineError> {
+    if config.engine != StorageEngine::Faulty {
+        return open_data_engine(io_runtime, config, config.engine, meter).await;
+    }
+
+    let faults = config.faults.clone().unwrap_or_default();
+    if faults.engine == StorageEngine::Faulty {
+        bail!(EngineError(
+            "storage.faults.engine cannot be faulty".to_string()
+        ));
+    }
+    let latency = faults
+        .latency()
+        .or_raise(|| EngineError("invalid storage faults".to_string()))?;
+
+    let inner = open_data_engine(io_runtime, config, faults.engine, meter).await?;
+    let engine = FaultyEngine::new(inner);
+    engine.set_faults(Faults {
+        error_rate: faults.error_one_in.map_or(0.0, |n| 1.0 / n.get() as f64),
+        latency,
+    });
+    log::warn!("storage engine injects faults: {faults:?}");
+    Ok(Arc::new(engine))
+}
+
+async fn open_data_engine(
+    io_runtime: &runtime::Runtime,
+    config: &StorageConfig,
+    engine: StorageEngine,
+    meter: Option<Meter>,
+) -> Result<Arc<dyn Engine>, EngineError> {
+    match engine {
+        StorageEngine::Foyer => {
+            let devices = config.devices();
+            let memory_capacity: ByteSize = config.memory_capacity.clone().into();
+            let memory_capacity = ByteSize(memory_capacity.as_u64() / devices.len() as u64);
+
+            let mut shards = vec![];
+            for (i, device) in devices.iter().enumerate() {
+                let name = if devices.len() == 1 {
+                    "foyer".to_string()
+                } else {
+                    format!("foyer-{i}")
+                };
+                let engine = FoyerEngine::try_new(
+                    io_runtime,
+                    &name,
+                    device,
+                    memory_capacity,
+                    &config.io_engine,
+                    meter.clone().map(OpenTelemetryMetricsRegistry::new),
+                )
+                .await;
+
+                match engine {
+                    Ok(engine) => shards.push(Shard::new(device.path.clone(), Arc::new(engine))),
+                    Err(err) => log::error!(
+                        "failed to open data device {} (left out): {err:?}",
+                        device.path.display()
+                    ),
+                }
+            }
+
+            if shards.is_empty() {
+                bail!(EngineError("failed to open any data device".to_string()));
+            }
+            Ok(Arc::new(ShardedEngine::new(shards)?))
+        }
+        StorageEngine::Memory => Ok(Arc::new(MemoryEngine::new(
+            config.memory_capacity.clone().into(),
+        ))),
+        StorageEngine::Faulty => unreachable!("the faulty engine wraps another engine"),
+    }
+}
@@ -0,0 +1,312 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use exn::Result;
+use exn::bail;
+use futures_util::FutureExt;
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+
+use super::Engine;
+use super::EngineError;
+use super::EngineStatistics;
+use super::EngineStatus;
+use super::EngineUsage;
+use super::Hit;
+
+/// How many consecutive failed operations take a device out of service.
+const MAX_CONSECUTIVE_ERRORS: usize = 8;
+
+/// The state of a data device of a [`ShardedEngine`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceStatus {
+    pub path: PathBuf,
+    pub capacity: u64,
+    pub healthy: bool,
+}
+
+/// An engine on a single data device.
+pub struct Shard {
+    path: PathBuf,
+    // seeds the rendezvous score, stable across restarts as long as the path is
+    seed: u32,
+    engine: Arc<dyn Engine>,
+    healthy: AtomicBool,
+    consecutive_errors: AtomicUsize,
+}
+
+impl Shard {
+    pub fn new(path: PathBuf, engine: Arc<dyn Engine>) -> Self {
+        let seed = mur3::murmurhash3_x86_32(path.as_os_str().as_encoded_bytes(), 0);
+        Self {
+            path,
+            seed,
+            engine,
+            healthy: AtomicBool::new(true),
+            consecutive_errors: AtomicUsize::new(0),
+        }
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    fn observe<T>(&self, result: Result<T, EngineError>) -> Result<T, EngineError> {
+        match &result {
+            Ok(_) => self.consecutive_errors.store(0, Ordering::Relaxed),
+            Err(err) => {
+                let errors = self.consecutive_errors.fetch_add(1, Ordering::Relaxed) + 1;
+                if errors >= MAX_CONSECUTIVE_ERRORS && self.healthy.swap(false, Ordering::Relaxed) {
+                    log::error!(
+                        "taking device {} out of service after {errors} consecutive errors: {err:?}",
+                        self.path.display()
+                    );
+                }
+            }
+        }
+        result
+    }
+}
+
+/// An engine that shards keys over several data devices.
+///
+/// Keys are assigned with rendezvous hashing weighted by device capacity and seeded by the device
+/// path, so that when a device is taken out of service, or left out on restart, only its own keys
+/// move to the remaining devices. A device is taken out
+/// of service after repeated errors and stays out until the node restarts.
+pub struct ShardedEngine {
+    shards: Vec<Shard>,
+}
+
+impl ShardedEngine {
+    /// Shard keys over the given devices, of which there must be at least one.
+    pub fn new(shards: Vec<Shard>) -> Result<Self, EngineError> {
+        if shards.is_empty() {
+            bail!(EngineError("no data device to shard keys over".to_string()));
+        }
+        Ok(Self { shards })
+    }
+
+    fn shard(&self, key: &[u8]) -> Result<&Shard, EngineError> {
+        let shard = self
+            .shards
+            .iter()
+            .filter(|shard| shard.is_healthy())
+            .map(|shard| {
+                let score = rendezvous_score(key, shard.seed, shard.engine.capacity());
+                (score, shard)
+            })
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, shard)| shard);
+
+        match shard {
+            Some(shard) => Ok(shard),
+            None => bail!(EngineError("no healthy data device available".to_string())),
+        }
+    }
+
+    pub fn devices(&self) -> Vec<DeviceStatus> {
+        self.shards
+            .iter()
+            .map(|shard| DeviceStatus {
+                path: shard.path.clone(),
+                capacity: shard.engine.capacity(),
+                healthy: shard.is_healthy(),
+            })
+            .collect()
+    }
+}
+
+/// The weighted rendezvous score of a key on the device with `seed`; the highest score wins.
+fn rendezvous_score(key: &[u8], seed: u32, weight: u64) -> f64 {
+    let hash = mur3::murmurhash3_x64_128(key, seed).0;
+    // map the hash into (0, 1) so that the logarithm is finite and negative
+    let unit = (hash as f64 + 1.0) / (u64::MAX as f64 + 2.0);
+    weight as f64 / -unit.ln()
+}
+
+impl Engine for ShardedEngine {
+    fn get<'a>(&'a self, key: &'a [u8]) -> BoxFuture<'a, Result<Option<Hit>, EngineError>> {
+        async move {
+            let shard = self.shard(key)?;
+            shard.observe(shard.engine.get(key).await)
+        }
+        .boxed()
+    }
+
+    fn put<'a>(&'a self, key: &'a [u8], value: &'a [u8]) -> BoxFuture<'a, Result<(), EngineError>> {
+        async move {
+            let shard = self.shard(key)?;
+            shard.observe(shard.engine.put(key, value).await)
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, key: &'a [u8]) -> BoxFuture<'a, Result<(), EngineError>> {
+        async move {
+            let shard = self.shard(key)?;
+            shard.observe(shard.engine.delete(key).await)
+        }
+        .boxed()
+    }
+
+    fn capacity(&self) -> u64 {
+        self.shards
+            .iter()
+            .filter(|shard| shard.is_healthy())
+            .map(|shard| shard.engine.capacity())
+            .sum()
+    }
+
+    fn statistics(&self) -> EngineStatistics {
+        self.shards
+            .iter()
+            .map(|shard| shard.engine.statistics())
+            .fold(EngineStatistics::default(), |acc, stats| EngineStatistics {
+                disk_read_bytes: acc.disk_read_bytes + stats.disk_read_bytes,
+                disk_write_bytes: acc.disk_write_bytes + stats.disk_write_bytes,
+                disk_read_ios: acc.disk_read_ios + stats.disk_read_ios,
+                disk_write_ios: acc.disk_write_ios + stats.disk_write_ios,
+                flush_dropped_entries: acc.flush_dropped_entries + stats.flush_dropped_entries,
+                memory_evictions: acc.memory_evictions + stats.memory_evictions,
+                disk_reclaimed_blocks: acc.disk_reclaimed_blocks + stats.disk_reclaimed_blocks,
+            })
+    }
+
+    fn usage(&self) -> EngineUsage {
+        self.shards
+            .iter()
+            .filter(|shard| shard.is_healthy())
+            .map(|shard| shard.engine.usage())
+            .fold(EngineUsage::default(), |acc, usage| acc + usage)
+    }
+
+    fn status(&self) -> EngineStatus {
+        let shard = self
+            .shards
+            .iter()
+            .find(|shard| shard.is_healthy())
+            .unwrap_or(&self.shards[0]); // never empty, see `new`
+        EngineStatus {
+            capacity: self.capacity(),
+            devices: self.devices(),
+            ..shard.engine.status()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use bytesize::ByteSize;
+
+    use super::*;
+    use crate::Faults;
+    use crate::FaultyEngine;
+    use crate::MemoryEngine;
+    use crate::runtime;
+
+    #[test]
+    fn test_degrade_on_failed_device() {
+        let runtime = runtime::make_runtime("test_runtime", "test_thread", 1);
+
+        runtime.block_on(async {
+            let faulty = Arc::new(FaultyEngine::new(Arc::new(MemoryEngine::new(
+                ByteSize::mib(2),
+            ))));
+            let engine = ShardedEngine::new(vec![
+                Shard::new(
+                    PathBuf::from("/dev0"),
+                    Arc::new(MemoryEngine::new(ByteSize::mib(2))),
+                ),
+                Shard::new(PathBuf::from("/dev1"), faulty.clone()),
+            ])
+            .unwrap();
+            assert_eq!(engine.capacity(), ByteSize::mib(2).as_u64());
+
+            let keys = (0..64).map(|i| format!("key-{i}")).collect::<Vec<_>>();
+            faulty.set_faults(Faults {
+                error_rate: 1.0,
+                ..Faults::default()
+            });
+
+            // keys on the faulty device fail until it is taken out of service
+            let mut failures = 0;
+            for key in &keys {
+                if engine.put(key.as_bytes(), b"value").await.is_err() {
+                    failures += 1;
+                }
+            }
+            assert_eq!(failures, MAX_CONSECUTIVE_ERRORS);
+            assert_eq!(engine.capacity(), ByteSize::mib(1).as_u64());
+            assert!(!engine.devices()[1].healthy);
+
+            // all keys are served by the remaining device
+            for key in &keys {
+                engine.put(key.as_bytes(), b"value").await.unwrap();
+                let value = engine.get(key.as_bytes()).await.unwrap();
+                assert_eq!(value.map(|hit| hit.value), Some(b"value".to_vec()));
+            }
+        });
+    }
+
+    #[test]
+    fn test_keys_stay_when_device_left_out() {
+        fn owners(paths: &[&str], keys: &[String]) -> Vec<PathBuf> {
+            let shards = paths
+                .iter()
+                .map(|path| {
+                    let engine = Arc::new(MemoryEngine::new(ByteSize::mib(1)));
+                    Shard::new(PathBuf::from(path), engine)
+                })
+                .collect();
+            let engine = ShardedEngine::new(shards).unwrap();
+            keys.iter()
+                .map(|key| engine.shard(key.as_bytes()).unwrap().path.clone())
+                .collect()
+        }
+
+        let keys = (0..1000).map(|i| format!("key-{i}")).collect::<Vec<_>>();
+        let all = owners(&["/dev0", "/dev1", "/dev2"], &keys);
+        // the first device fails to open on restart, which shifts the others in the list
+        let rest = owners(&["/dev1", "/dev2"], &keys);
+        for (before, after) in all.iter().zip(&rest) {
+            if before != Path::new("/dev0") {
+                assert_eq!(before, after);
+            }
+        }
+
+        assert!(ShardedEngine::new(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_rendezvous_follows_capacity() {
+        let keys = (0..10000).map(|i| format!("key-{i}")).collect::<Vec<_>>();
+        let on_large = keys
+            .iter()
+            .filter(|key| {
+                rendezvous_score(key.as_bytes(), 1, 3) > rendezvous_score(key.as_bytes(), 0, 1)
+            })
+            .count();
+        // the larger device takes about three quarters of the keys
+        assert!((7000..8000).contains(&on_large), "{on_large}");
+    }
+}
@@ -0,0 +1,428 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Occupancy and evictions of the storage engines.
+//!
+//! foyer does not expose how full its disk tier is, but publishes the state of its blocks as
+//! metrics. [`UsageRegistry`] sits in front of the metrics registry handed to foyer and keeps a
+//! copy of the few series that tell the occupancy. Entries leaving the memory tier are counted by
+//! [`EvictionListener`].
+//!
+//! The disk tier is therefore only reported in blocks. Live bytes, the number of entries on disk
+//! and the bytes waiting to be flushed are not reported, as foyer tracks none of them.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use foyer::Event;
+use foyer::EventListener;
+use mixtrics::metrics::BoxedCounter;
+use mixtrics::metrics::BoxedCounterVec;
+use mixtrics::metrics::BoxedGauge;
+use mixtrics::metrics::BoxedGaugeVec;
+use mixtrics::metrics::BoxedHistogramVec;
+use mixtrics::metrics::BoxedRegistry;
+use mixtrics::metrics::CounterOps;
+use mixtrics::metrics::CounterVecOps;
+use mixtrics::metrics::GaugeOps;
+use mixtrics::metrics::GaugeVecOps;
+use mixtrics::metrics::RegistryOps;
+use serde::Serialize;
+
+/// Point-in-time occupancy of an engine.
+///
+/// The disk tier is measured in blocks: foyer reports neither the live bytes nor the number of
+/// entries on disk, nor the bytes waiting to be flushed. Flush backpressure shows up instead as
+/// [`EngineStatistics::flush_dropped_entries`](super::EngineStatistics::flush_dropped_entries).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EngineUsage {
+    /// Bytes of the entries held in memory.
+    pub memory_used_bytes: u64,
+    /// The limit of `memory_used_bytes`.
+    pub memory_capacity_bytes: u64,
+    /// The number of entries held in memory.
+    pub memory_entries: u64,
+    /// Bytes of the disk blocks that are not clean, i.e. being written or holding entries.
+    ///
+    /// This is allocated space, not live bytes: evicted, overwritten and deleted entries keep
+    /// their space until their block is reclaimed.
+    pub disk_allocated_bytes: u64,
+    /// The capacity of the disk tier.
+    pub disk_capacity_bytes: u64,
+    /// The disk blocks by state.
+    pub disk_blocks: BlockUsage,
+}
+
+impl EngineUsage {
+    /// The share of disk blocks that hold entries or are being written, in `[0, 1]`.
+    pub fn block_fill_ratio(&self) -> f64 {
+        let total = self.disk_blocks.total();
+        if total == 0 {
+            0.0
+        } else {
+            (total - self.disk_blocks.clean) as f64 / total as f64
+        }
+    }
+}
+
+impl std::ops::Add for EngineUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            memory_used_bytes: self.memory_used_bytes + other.memory_used_bytes,
+            memory_capacity_bytes: self.memory_capacity_bytes + other.memory_capacity_bytes,
+            memory_entries: self.memory_entries + other.memory_entries,
+            disk_allocated_bytes: self.disk_allocated_bytes + other.disk_allocated_bytes,
+            disk_capacity_bytes: self.disk_capacity_bytes + other.disk_capacity_bytes,
+            disk_blocks: BlockUsage {
+                clean: self.disk_blocks.clean + other.disk_blocks.clean,
+                writing: self.disk_blocks.writing + other.disk_blocks.writing,
+                evictable: self.disk_blocks.evictable + other.disk_blocks.evictable,
+                reclaiming: self.disk_blocks.reclaiming + other.disk_blocks.reclaiming,
+            },
+        }
+    }
+}
+
+/// The number of disk blocks in each state of their lifecycle.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BlockUsage {
+    /// Empty blocks ready to be written.
+    pub clean: u64,
+    /// Blocks being filled by the flushers.
+    pub writing: u64,
+    /// Full blocks that hold entries.
+    pub evictable: u64,
+    /// Blocks being evicted to become clean.
+    pub reclaiming: u64,
+}
+
+impl BlockUsage {
+    pub fn total(&self) -> u64 {
+        self.clean + self.writing + self.evictable + self.reclaiming
+    }
+}
+
+/// The series of foyer metrics kept by a [`UsageRegistry`].
+#[derive(Debug, Default)]
+pub(crate) struct UsageTracker {
+    blocks_clean: Arc<AtomicU64>,
+    blocks_writing: Arc<AtomicU64>,
+    blocks_evictable: Arc<AtomicU64>,
+    blocks_reclaiming: Arc<AtomicU64>,
+    block_size: Arc<AtomicU64>,
+    flush_dropped_entries: Arc<AtomicU64>,
+    disk_reclaimed_blocks: Arc<AtomicU64>,
+    memory_evicted_entries: AtomicU64,
+    memory_replaced_entries: AtomicU64,
+    memory_removed_entries: AtomicU64,
+}
+
+impl UsageTracker {
+    pub fn blocks(&self) -> BlockUsage {
+        BlockUsage {
+            clean: self.blocks_clean.load(Ordering::Relaxed),
+            writing: self.blocks_writing.load(Ordering::Relaxed),
+            evictable: self.blocks_evictable.load(Ordering::Relaxed),
+            reclaiming: self.blocks_reclaiming.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn block_size(&self) -> u64 {
+        self.block_size.load(Ordering::Relaxed)
+    }
+
+    /// Entries dropped on their way to disk because the flushers could not keep up.
+    pub fn flush_dropped_entries(&self) -> u64 {
+        self.flush_dropped_entries.load(Ordering::Relaxed)
+    }
+
+    /// Disk blocks evicted to make room for new entries.
+    pub fn disk_reclaimed_blocks(&self) -> u64 {
+        self.disk_reclaimed_blocks.load(Ordering::Relaxed)
+    }
+
+    /// Entries that left the memory tier, by reason.
+    pub fn memory_evictions(&self) -> MemoryEvictions {
+        MemoryEvictions {
+            evicted: self.memory_evicted_entries.load(Ordering::Relaxed),
+            replaced: self.memory_replaced_entries.load(Ordering::Relaxed),
+            removed: self.memory_removed_entries.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counters of the entries that left the memory tier, by reason.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryEvictions {
+    /// Evicted to make room; the disk engine writes them to disk.
+    pub evicted: u64,
+    /// Overwritten by a newer value of the same key.
+    pub replaced: u64,
+    /// Deleted, or dropped when the cache was cleared.
+    pub removed: u64,
+}
+
+impl std::ops::Add for MemoryEvictions {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            evicted: self.evicted + other.evicted,
+            replaced: self.replaced + other.replaced,
+            removed: self.removed + other.removed,
+        }
+    }
+}
+
+/// Counts the entries that leave the memory tier into a tracker.
+pub(crate) struct EvictionListener {
+    tracker: Arc<UsageTracker>,
+}
+
+impl EvictionListener {
+    pub fn new(tracker: Arc<UsageTracker>) -> Self {
+        Self { tracker }
+    }
+}
+
+impl EventListener for EvictionListener {
+    type Key = Vec<u8>;
+    type Value = Vec<u8>;
+
+    fn on_leave(&self, reason: Event, _: &Vec<u8>, _: &Vec<u8>) {
+        let counter = match reason {
+            Event::Evict => &self.tracker.memory_evicted_entries,
+            Event::Replace => &self.tracker.memory_replaced_entries,
+            Event::Remove | Event::Clear => &self.tracker.memory_removed_entries,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A metrics registry that forwards to `inner` and copies occupancy series into a tracker.
+#[derive(Debug)]
+pub(crate) struct UsageRegistry {
+    inner: BoxedRegistry,
+    tracker: Arc<UsageTracker>,
+}
+
+impl UsageRegistry {
+    pub fn new(inner: BoxedRegistry, tracker: Arc<UsageTracker>) -> Self {
+        Self { inner, tracker }
+    }
+}
+
+impl RegistryOps for UsageRegistry {
+    fn register_counter_vec(
+        &self,
+        name: Cow<'static, str>,
+        desc: Cow<'static, str>,
+        label_names: &'static [&'static str],
+    ) -> BoxedCounterVec {
+        let inner = self
+            .inner
+            .register_counter_vec(name.clone(), desc, label_names);
+        match name.as_ref() {
+            "foyer_storage_inner_op_total" => Box::new(TeeCounterVec {
+                inner,
+                tracker: self.tracker.clone(),
+            }),
+            _ => inner,
+        }
+    }
+
+    fn register_gauge_vec(
+        &self,
+        name: Cow<'static, str>,
+        desc: Cow<'static, str>,
+        label_names: &'static [&'static str],
+    ) -> BoxedGaugeVec {
+        let inner = self
+            .inner
+            .register_gauge_vec(name.clone(), desc, label_names);
+        match name.as_ref() {
+            "foyer_storage_block_engine_block" | "foyer_storage_block_engine_block_size_bytes" => {
+                Box::new(TeeGaugeVec {
+                    name,
+                    inner,
+                    tracker: self.tracker.clone(),
+                })
+            }
+            _ => inner,
+        }
+    }
+
+    fn register_histogram_vec(
+        &self,
+        name: Cow<'static, str>,
+        desc: Cow<'static, str>,
+        label_names: &'static [&'static str],
+    ) -> BoxedHistogramVec {
+        self.inner.register_histogram_vec(name, desc, label_names)
+    }
+
+    fn register_histogram_vec_with_buckets(
+        &self,
+        name: Cow<'static, str>,
+        desc: Cow<'static, str>,
+        label_names: &'static [&'static str],
+        buckets: Vec<f64>,
+    ) -> BoxedHistogramVec {
+        self.inner
+            .register_histogram_vec_with_buckets(name, desc, label_names, buckets)
+    }
+}
+
+#[derive(Debug)]
+struct TeeCounterVec {
+    inner: BoxedCounterVec,
+    tracker: Arc<UsageTracker>,
+}
+
+impl CounterVecOps for TeeCounterVec {
+    fn counter(&self, labels: &[Cow<'static, str>]) -> BoxedCounter {
+        let inner = self.inner.counter(labels);
+        // labels are [name, op]
+        match labels.get(1).map(|op| op.as_ref()) {
+            Some("buffer_overflow" | "channel_overflow") => Box::new(TeeCounter {
+                inner,
+                local: self.tracker.flush_dropped_entries.clone(),
+            }),
+            _ => inner,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TeeCounter {
+    inner: BoxedCounter,
+    local: Arc<AtomicU64>,
+}
+
+impl CounterOps for TeeCounter {
+    fn increase(&self, val: u64) {
+        self.local.fetch_add(val, Ordering::Relaxed);
+        self.inner.increase(val);
+    }
+}
+
+#[derive(Debug)]
+struct TeeGaugeVec {
+    name: Cow<'static, str>,
+    inner: BoxedGaugeVec,
+    tracker: Arc<UsageTracker>,
+}
+
+impl GaugeVecOps for TeeGaugeVec {
+    fn gauge(&self, labels: &[Cow<'static, str>]) -> BoxedGauge {
+        let inner = self.inner.gauge(labels);
+        let tracker = &self.tracker;
+        let local = if self.name == "foyer_storage_block_engine_block_size_bytes" {
+            Some(&tracker.block_size)
+        } else {
+            // labels are [name, type]
+            match labels.get(1).map(|ty| ty.as_ref()) {
+                Some("clean") => Some(&tracker.blocks_clean),
+                Some("writing") => Some(&tracker.blocks_writing),
+                Some("evictable") => Some(&tracker.blocks_evictable),
+                Some("reclaiming") => Some(&tracker.blocks_reclaiming),
+                _ => None,
+            }
+        };
+        let entered = (labels.get(1).map(|ty| ty.as_ref()) == Some("reclaiming"))
+            .then(|| tracker.disk_reclaimed_blocks.clone());
+        match local {
+            Some(local) => Box::new(TeeGauge {
+                inner,
+                local: local.clone(),
+                entered,
+            }),
+            None => inner,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TeeGauge {
+    inner: BoxedGauge,
+    local: Arc<AtomicU64>,
+    /// Counts the increases of the gauge, e.g. blocks entering the reclaiming state.
+    entered: Option<Arc<AtomicU64>>,
+}
+
+impl GaugeOps for TeeGauge {
+    fn increase(&self, val: u64) {
+        self.local.fetch_add(val, Ordering::Relaxed);
+        if let Some(entered) = &self.entered {
+            entered.fetch_add(val, Ordering::Relaxed);
+        }
+        self.inner.increase(val);
+    }
+
+    fn decrease(&self, val: u64) {
+        let _ = self
+            .local
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| {
+                Some(v.saturating_sub(val))
+            });
+        self.inner.decrease(val);
+    }
+
+    fn absolute(&self, val: u64) {
+        self.local.store(val, Ordering::Relaxed);
+        self.inner.absolute(val);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use foyer_common::metrics::Metrics;
+    use mixtrics::registry::noop::NoopMetricsRegistry;
+
+    use super::*;
+
+    #[test]
+    fn test_track_foyer_series() {
+        let tracker = Arc::new(UsageTracker::default());
+        let registry: BoxedRegistry = Box::new(UsageRegistry::new(
+            Box::new(NoopMetricsRegistry),
+            tracker.clone(),
+        ));
+        // the series are matched by name, so a rename in foyer leaves the tracker at zero
+        let metrics = Metrics::new("test", &registry);
+
+        metrics.storage_block_engine_block_clean.absolute(3);
+        metrics.storage_block_engine_block_writing.increase(1);
+        metrics.storage_block_engine_block_evictable.increase(2);
+        metrics.storage_block_engine_block_reclaiming.increase(1);
+        metrics.storage_block_engine_block_size_bytes.absolute(4096);
+        metrics.storage_queue_buffer_overflow.increase(1);
+        metrics.storage_queue_channel_overflow.increase(2);
+
+        let blocks = BlockUsage {
+            clean: 3,
+            writing: 1,
+            evictable: 2,
+            reclaiming: 1,
+        };
+        assert_eq!(tracker.blocks(), blocks);
+        assert_eq!(tracker.block_size(), 4096);
+        assert_eq!(tracker.flush_dropped_entries(), 3);
+        assert_eq!(tracker.disk_reclaimed_blocks(), 1);
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use exn::Result;
+use exn::ResultExt;
+
+use crate::GossipError;
+use crate::gossip::GossipMessage;
+
+/// The wire encoding of gossip messages, negotiated via the `Content-Type` header.
+///
+/// Nodes prefer [`GossipCodec::Postcard`] and fall back to [`GossipCodec::Json`] when a peer
+/// rejects the binary encoding. A response is always encoded with the codec of its request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GossipCodec {
+    Json,
+    Postcard,
+}
+
+impl GossipCodec {
+    pub const JSON_CONTENT_TYPE: &str = "application/json";
+    pub const POSTCARD_CONTENT_TYPE: &str = "application/x-postcard";
+
+    /// Resolves the codec from a `Content-Type` header value, ignoring any parameters.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next().unwrap_or_default().trim();
+        if mime.eq_ignore_ascii_case(Self::JSON_CONTENT_TYPE) {
+            Some(GossipCodec::Json)
+        } else if mime.eq_ignore_ascii_case(Self::POSTCARD_CONTENT_TYPE) {
+            Some(GossipCodec::Postcard)
+        } else {
+            None
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            GossipCodec::Json => Self::JSON_CONTENT_TYPE,
+            GossipCodec::Postcard => Self::POSTCARD_CONTENT_TYPE,
+        }
+    }
+
+    pub fn encode(&self, message: &GossipMessage) -> Result<Vec<u8>, GossipError> {
+        let make_error = || GossipError(format!("failed to encode gossip message as {self:?}"));
+        match self {
+            GossipCodec::Json => serde_json::to_vec(message).or_raise(make_error),
+            GossipCodec::Postcard => postcard::to_stdvec(message).or_raise(make_error),
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> Result<GossipMessage, GossipError> {
+        let make_error = || GossipError(format!("failed to decode gossip message as {self:?}"));
+        match self {
+            GossipCodec::Json => serde_json::from_slice(bytes).or_raise(make_error),
+            GossipCodec::Postcard => postcard::from_bytes(bytes).or_raise(make_error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::Timestamp;
+    use reqwest::Url;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::MemberDigest;
+    use crate::MemberState;
+    use crate::MemberStatus;
+    use crate::NodeInfo;
+
+    fn make_member(id: u64) -> MemberState {
+        MemberState {
+            info: NodeInfo::new(
+                Uuid::from_u64_pair(0, id),
+                "cluster".to_string(),
+                Url::parse("http://127.0.0.1:7654").unwrap(),
+                Url::parse("http://127.0.0.1:7655").unwrap(),
+            ),
+            status: MemberStatus::Alive,
+            heartbeat: Timestamp::constant(12345, 6789),
+        }
+    }
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(
+            GossipCodec::from_content_type("application/json; charset=utf-8"),
+            Some(GossipCodec::Json)
+        );
+        assert_eq!(
+            GossipCodec::from_content_type("application/x-postcard"),
+            Some(GossipCodec::Postcard)
+        );
+        assert_eq!(GossipCodec::from_content_type("text/plain"), None);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let messages = [
+            GossipMessage::Ping(make_member(1).info),
+            GossipMessage::Sync {
+                members: vec![make_member(1), make_member(2)],
+            },
+            GossipMessage::Digest {
+                digests: vec![MemberDigest::from(&make_member(1))],
+            },
+            GossipMessage::Delta {
+                members: vec![make_member(2)],
+                wanted: vec![Uuid::from_u64_pair(0, 3)],
+            },
+        ];
+
+        for codec in [GossipCodec::Json, GossipCodec::Postcard] {
+            for message in &messages {
+                let bytes = codec.encode(message).unwrap();
+                assert_eq!(&codec.decode(&bytes).unwrap(), message);
+            }
+        }
+    }
+
+    #[test]
+    fn test_postcard_is_compact() {
+        let digests = (0..200)
+            .map(|i| MemberDigest::from(&make_member(i)))
+            .collect::<Vec<_>>();
+        let message = GossipMessage::Digest { digests };
+
+        let json = GossipCodec::Json.encode(&message).unwrap();
+        let postcard = GossipCodec::Postcard.encode(&message).unwrap();
+        assert!(postcard.len() * 2 < json.len());
+    }
+}
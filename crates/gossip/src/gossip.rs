@@ -14,7 +14,6 @@
 
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
 
 use arc_swap::ArcSwap;
 use backon::ConstantBuilder;
@@ -25,35 +24,31 @@ use exn::bail;
 use exn::ensure;
 use fastimer::MakeDelayExt;
 use jiff::Timestamp;
+use mea::broadcast::overflow;
 use mea::shutdown::ShutdownRecv;
+use percas_core::GossipTiming;
 use percas_core::JoinHandle;
 use percas_core::Runtime;
 use percas_core::node_file_path;
 use percas_core::timer;
+use percas_ring::HashRing;
 use rand::Rng;
 use rand::SeedableRng;
 use reqwest::Client;
+use reqwest::StatusCode;
 use reqwest::Url;
+use reqwest::header::CONTENT_TYPE;
 use serde::Deserialize;
 use serde::Serialize;
 use uuid::Uuid;
 
 use crate::GossipError;
+use crate::codec::GossipCodec;
+use crate::member::MemberDigest;
 use crate::member::MemberState;
 use crate::member::MemberStatus;
 use crate::member::Membership;
 use crate::node::NodeInfo;
-use crate::ring::HashRing;
-
-const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(1);
-const DEFAULT_SYNC_INTERVAL: Duration = Duration::from_secs(5);
-
-const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_secs(1);
-const DEFAULT_RETRIES: usize = 3;
-
-const DEFAULT_REBUILD_RING_INTERVAL: Duration = Duration::from_secs(5);
-
-const DEFAULT_MEMBER_DEADLINE: Duration = Duration::from_secs(30);
 
 pub type GossipFuture = JoinHandle<Result<(), GossipError>>;
 
@@ -62,22 +57,74 @@ pub struct GossipState {
     dir: PathBuf,
     initial_peers: Vec<Url>,
     current_node: ArcSwap<NodeInfo>,
+    timing: GossipTiming,
     transport: Transport,
 
     membership: ArcSwap<Membership>,
-    ring: ArcSwap<HashRing<Uuid>>,
+    ring: ArcSwap<RingSnapshot>,
+    ring_changes: RingChanges,
 }
 
-impl GossipState {
-    pub fn new(current_node: NodeInfo, initial_peers: Vec<Url>, dir: PathBuf) -> Self {
+/// Broadcasts the content hash of every rebuilt ring.
+struct RingChanges(overflow::Sender<u64>);
+
+impl std::fmt::Debug for RingChanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RingChanges").finish_non_exhaustive()
+    }
+}
+
+/// The hash ring of the cluster, stamped with an epoch and a content hash.
+///
+/// The epoch is local to a node and advances every time the content hash changes. The content
+/// hash covers the members and their statuses, i.e. everything routing depends on, so it can be
+/// compared across nodes to tell whether they route keys the same way.
+#[derive(Debug)]
+pub struct RingSnapshot {
+    pub epoch: u64,
+    pub hash: u64,
+    pub ring: HashRing<Uuid>,
+}
+
+impl RingSnapshot {
+    fn empty() -> Self {
         Self {
+            epoch: 0,
+            hash: ring_hash(&Membership::default()),
+            ring: HashRing::default(),
+        }
+    }
+}
+
+fn ring_hash(membership: &Membership) -> u64 {
+    let mut bytes = Vec::with_capacity(membership.members().len() * 17);
+    for (id, member) in membership.members() {
+        bytes.extend_from_slice(id.as_bytes());
+        bytes.push(match member.status {
+            MemberStatus::Alive => 0,
+            MemberStatus::Dead => 1,
+        });
+    }
+    mur3::murmurhash3_x64_128(&bytes, 0).0
+}
+
+impl GossipState {
+    pub fn new(
+        current_node: NodeInfo,
+        initial_peers: Vec<Url>,
+        dir: PathBuf,
+        timing: GossipTiming,
+    ) -> Result<Self, GossipError> {
+        Ok(Self {
             dir,
             initial_peers,
             current_node: ArcSwap::new(Arc::new(current_node)),
             membership: ArcSwap::new(Arc::new(Membership::default())),
-            transport: Transport::new(),
-            ring: ArcSwap::new(Arc::new(HashRing::default())),
-        }
+            timing,
+            transport: Transport::new(&timing)?,
+            ring: ArcSwap::new(Arc::new(RingSnapshot::empty())),
+            ring_changes: RingChanges(overflow::channel(1).0),
+        })
     }
 
     pub fn current(&self) -> NodeInfo {
@@ -88,10 +135,15 @@ impl GossipState {
         self.membership.load_full()
     }
 
-    pub fn ring(&self) -> Arc<HashRing<Uuid>> {
+    pub fn ring(&self) -> Arc<RingSnapshot> {
         self.ring.load_full()
     }
 
+    /// Subscribe to the content hashes of rings rebuilt from now on.
+    pub fn watch_ring(&self) -> overflow::Receiver<u64> {
+        self.ring_changes.0.subscribe()
+    }
+
     /// Start the gossip protocol.
     pub async fn start(
         self: Arc<Self>,
@@ -127,7 +179,7 @@ impl GossipState {
         let ping_fut = rt.spawn(async move {
             let fut = async move {
                 let state = state_clone;
-                let mut ticker = timer().interval(DEFAULT_PING_INTERVAL);
+                let mut ticker = timer().interval(state.timing.ping_interval);
                 loop {
                     ticker.tick().await;
 
@@ -167,7 +219,7 @@ impl GossipState {
         let anti_entropy_fut = rt.spawn(async move {
             let fut = async move {
                 let state = state_clone;
-                let mut ticker = timer().interval(DEFAULT_SYNC_INTERVAL);
+                let mut ticker = timer().interval(state.timing.sync_interval);
                 loop {
                     ticker.tick().await;
                     let membership = state.membership();
@@ -199,13 +251,13 @@ impl GossipState {
         });
         gossip_futs.push(anti_entropy_fut);
 
-        // Rebuild ring
+        // Rebuild ring; membership changes rebuild it right away, this is a safety net
         let state_clone = self.clone();
         let shutdown_rx_clone = shutdown_rx.clone();
         let rebuild_ring_fut = rt.spawn(async move {
             let fut = async move {
                 let state = state_clone;
-                let mut ticker = timer().interval(DEFAULT_REBUILD_RING_INTERVAL);
+                let mut ticker = timer().interval(state.timing.rebuild_ring_interval);
                 loop {
                     ticker.tick().await;
                     state.rebuild_ring();
@@ -228,13 +280,12 @@ impl GossipState {
         let remove_dead_members_fut = rt.spawn(async move {
             let fut = async move {
                 let state = state_clone;
-                let mut ticker = timer().interval(DEFAULT_MEMBER_DEADLINE);
+                let mut ticker = timer().interval(state.timing.member_deadline);
                 loop {
                     ticker.tick().await;
                     let dead_members = state.remove_dead_members();
                     if !dead_members.is_empty() {
                         log::info!("removed dead members: {dead_members:?}");
-                        state.rebuild_ring();
                     }
                 }
             };
@@ -257,47 +308,82 @@ impl GossipState {
         let result = match message {
             GossipMessage::Ping(info) => {
                 let mut membership = (**self.membership.load()).clone();
-                membership.update_member(MemberState {
+                let changed = membership.update_member(MemberState {
                     info: info.clone(),
                     status: MemberStatus::Alive,
                     heartbeat: Timestamp::now(),
                 });
-                self.membership.store(Arc::new(membership));
+                self.store_membership(membership, changed);
 
                 // Respond with an ack
                 Some(GossipMessage::Ack(self.current()))
             }
             GossipMessage::Ack(info) => {
                 let mut membership = (**self.membership.load()).clone();
-                membership.update_member(MemberState {
+                let changed = membership.update_member(MemberState {
                     info: info.clone(),
                     status: MemberStatus::Alive,
                     heartbeat: Timestamp::now(),
                 });
-                self.membership.store(Arc::new(membership));
+                self.store_membership(membership, changed);
 
                 None
             }
             GossipMessage::Sync { members } => {
                 let mut membership = (**self.membership.load()).clone();
+                let mut changed = false;
                 for member in members {
-                    membership.update_member(member);
+                    changed |= membership.update_member(member);
                 }
 
                 // Ensure the current node is alive
-                membership.update_member(MemberState {
+                changed |= membership.update_member(MemberState {
                     info: self.current(),
                     status: MemberStatus::Alive,
                     heartbeat: Timestamp::now(),
                 });
 
-                self.membership.store(Arc::new(membership.clone()));
+                self.store_membership(membership.clone(), changed);
 
                 // Respond with the current membership
                 Some(GossipMessage::Sync {
                     members: membership.into_members().into_values().collect(),
                 })
             }
+            GossipMessage::Digest { digests } => {
+                let mut membership = (**self.membership.load()).clone();
+
+                // Ensure the current node is alive
+                let changed = membership.update_member(MemberState {
+                    info: self.current(),
+                    status: MemberStatus::Alive,
+                    heartbeat: Timestamp::now(),
+                });
+
+                self.store_membership(membership.clone(), changed);
+
+                // Respond with what the peer is missing and ask for what we are missing
+                let (members, wanted) = membership.diff(&digests);
+                Some(GossipMessage::Delta { members, wanted })
+            }
+            GossipMessage::Delta { members, wanted } => {
+                let mut membership = (**self.membership.load()).clone();
+                let mut changed = false;
+                for member in members {
+                    changed |= membership.update_member(member);
+                }
+                self.store_membership(membership.clone(), changed);
+
+                // Respond with the members the peer asked for
+                let members = wanted
+                    .iter()
+                    .filter_map(|id| membership.members().get(id).cloned())
+                    .collect();
+                Some(GossipMessage::Delta {
+                    members,
+                    wanted: vec![],
+                })
+            }
         };
 
         if self.membership.load().is_dead(self.current().node_id) {
@@ -322,7 +408,7 @@ impl GossipState {
             .iter()
             .filter_map(|(_, member)| {
                 if member.status == MemberStatus::Dead
-                    && member.heartbeat + DEFAULT_MEMBER_DEADLINE < Timestamp::now()
+                    && member.heartbeat + self.timing.member_deadline < Timestamp::now()
                 {
                     Some(member.info.clone())
                 } else {
@@ -335,25 +421,18 @@ impl GossipState {
             members.remove_member(dead_member.node_id);
         }
 
-        self.membership.store(Arc::new(members));
+        let changed = !dead_members.is_empty();
+        self.store_membership(members, changed);
 
         dead_members
     }
 
     async fn ping(&self, peer: NodeInfo) {
         let message = GossipMessage::Ping(self.current());
-        let do_send = || async {
-            self.transport
-                .send(&peer.advertise_ctrl_url, &message)
-                .await
-                .inspect_err(|e| log::error!("failed to send ping message: {e:?}"))
-        };
-        let with_retry = do_send.retry(
-            ConstantBuilder::new()
-                .with_delay(DEFAULT_RETRY_INTERVAL)
-                .with_max_times(DEFAULT_RETRIES),
-        );
-        if let Ok(msg @ GossipMessage::Ack(_)) = with_retry.await {
+        let result = self
+            .send_with_retry(&peer.advertise_ctrl_url, &message)
+            .await;
+        if let Ok(msg @ GossipMessage::Ack(_)) = result {
             self.handle_message(msg);
         } else {
             self.mark_dead(&peer);
@@ -361,69 +440,91 @@ impl GossipState {
     }
 
     async fn sync(&self, peer: NodeInfo) {
-        let message = GossipMessage::Sync {
-            members: self.membership().members().values().cloned().collect(),
+        if !self.sync_with(&peer.advertise_ctrl_url).await {
+            self.mark_dead(&peer);
+        }
+    }
+
+    /// Run an anti-entropy round with the peer at the given url. Returns whether it succeeded.
+    ///
+    /// Peers first exchange digests and then transfer only the members that differ in both
+    /// directions. Peers that predate the digest exchange get a full sync instead.
+    async fn sync_with(&self, url: &Url) -> bool {
+        let message = GossipMessage::Digest {
+            digests: self.membership().digests(),
+        };
+        let Ok(msg @ GossipMessage::Delta { .. }) = self.send_with_retry(url, &message).await
+        else {
+            log::debug!("digest exchange with {url} failed; falling back to full sync");
+            let message = GossipMessage::Sync {
+                members: self.membership().members().values().cloned().collect(),
+            };
+            return match self.transport.send(url, &message).await {
+                Ok(msg @ GossipMessage::Sync { .. }) => {
+                    self.handle_message(msg);
+                    true
+                }
+                _ => false,
+            };
         };
+
+        // Push back the members the peer asked for
+        match self.handle_message(msg) {
+            Some(GossipMessage::Delta { members, .. }) if !members.is_empty() => {
+                let message = GossipMessage::Delta {
+                    members,
+                    wanted: vec![],
+                };
+                self.send_with_retry(url, &message).await.is_ok()
+            }
+            _ => true,
+        }
+    }
+
+    async fn send_with_retry(
+        &self,
+        url: &Url,
+        message: &GossipMessage,
+    ) -> Result<GossipMessage, GossipError> {
         let do_send = || async {
             self.transport
-                .send(&peer.advertise_ctrl_url, &message)
+                .send(url, message)
                 .await
-                .inspect_err(|e| log::error!("failed to send sync message: {e:?}"))
+                .inspect_err(|e| log::error!("failed to send {} message: {e:?}", message.kind()))
         };
-        let with_retry = do_send.retry(
-            ConstantBuilder::new()
-                .with_delay(DEFAULT_RETRY_INTERVAL)
-                .with_max_times(DEFAULT_RETRIES),
-        );
-        if let Ok(msg @ GossipMessage::Sync { .. }) = with_retry.await {
-            self.handle_message(msg);
-        } else {
-            self.mark_dead(&peer);
-        }
+        do_send
+            .retry(
+                ConstantBuilder::new()
+                    .with_delay(self.timing.retry_interval)
+                    .with_max_times(self.timing.retries),
+            )
+            .await
     }
 
     async fn fast_bootstrap(&self) {
         for peer in &self.initial_peers {
             let message = GossipMessage::Ping(self.current());
-            let do_send = || async {
-                self.transport
-                    .send(peer, &message)
-                    .await
-                    .inspect_err(|e| log::error!("failed to send ping message: {e:?}"))
-            };
-            let with_retry = do_send.retry(
-                ConstantBuilder::new()
-                    .with_delay(DEFAULT_RETRY_INTERVAL)
-                    .with_max_times(DEFAULT_RETRIES),
-            );
-            if let Ok(msg @ GossipMessage::Ack(_)) = with_retry.await {
+            if let Ok(msg @ GossipMessage::Ack(_)) = self.send_with_retry(peer, &message).await {
                 self.handle_message(msg);
             }
         }
 
         for peer in &self.initial_peers {
-            let message = GossipMessage::Sync {
-                members: self.membership().members().values().cloned().collect(),
-            };
-            let do_send = || async {
-                self.transport
-                    .send(peer, &message)
-                    .await
-                    .inspect_err(|e| log::error!("failed to send sync message: {e:?}"))
-            };
-            let with_retry = do_send.retry(
-                ConstantBuilder::new()
-                    .with_delay(DEFAULT_RETRY_INTERVAL)
-                    .with_max_times(DEFAULT_RETRIES),
-            );
-            if let Ok(msg @ GossipMessage::Sync { .. }) = with_retry.await {
-                self.handle_message(msg);
-            }
+            self.sync_with(peer).await;
         }
 
         self.rebuild_ring();
     }
 
+    /// Store the membership and rebuild the ring right away if it has changed.
+    fn store_membership(&self, membership: Membership, changed: bool) {
+        self.membership.store(Arc::new(membership));
+        if changed {
+            self.rebuild_ring();
+        }
+    }
+
+    /// Rebuild the ring from the current membership, advancing its epoch if the content changed.
     fn rebuild_ring(&self) {
         // Ensure the current node is alive
         let mut membership = (**self.membership.load()).clone();
@@ -433,22 +534,44 @@ impl GossipState {
             heartbeat: Timestamp::now(),
         });
 
-        self.ring.store(Arc::new(HashRing::from(
-            membership.members().keys().cloned(),
-        )));
+        let hash = ring_hash(&membership);
+        if self.ring.load().hash == hash {
+            return;
+        }
+
+        let ring = HashRing::from(membership.members().keys().cloned());
+        let previous = self.ring.rcu(|current| {
+            if current.hash == hash {
+                current.clone()
+            } else {
+                Arc::new(RingSnapshot {
+                    epoch: current.epoch + 1,
+                    hash,
+                    ring: ring.clone(),
+                })
+            }
+        });
+        if previous.hash != hash {
+            self.ring_changes.0.send(hash);
+            log::info!(
+                "rebuilt ring at epoch {} with hash {hash:016x}",
+                previous.epoch + 1
+            );
+        }
     }
 
     fn mark_dead(&self, peer: &NodeInfo) {
         let mut members = (**self.membership.load()).clone();
+        let mut changed = false;
         if let Some(last_seen) = members.members().get(&peer.node_id).map(|m| m.heartbeat) {
             let member = MemberState {
                 info: peer.clone(),
                 status: MemberStatus::Dead,
                 heartbeat: last_seen,
             };
-            members.update_member(member);
+            changed = members.update_member(member);
         }
-        self.membership.store(Arc::new(members));
+        self.store_membership(members, changed);
     }
 }
 
@@ -456,7 +579,31 @@ impl GossipState {
 pub enum GossipMessage {
     Ping(NodeInfo),
     Ack(NodeInfo),
-    Sync { members: Vec<MemberState> },
+    /// Full membership exchange; kept for peers that predate the digest exchange.
+    Sync {
+        members: Vec<MemberState>,
+    },
+    /// Starts an anti-entropy round with a digest of the sender's membership.
+    Digest {
+        digests: Vec<MemberDigest>,
+    },
+    /// Carries the members that differ, and the ids of members the sender wants in return.
+    Delta {
+        members: Vec<MemberState>,
+        wanted: Vec<Uuid>,
+    },
+}
+
+impl GossipMessage {
+    fn kind(&self) -> &'static str {
+        match self {
+            GossipMessage::Ping(_) => "ping",
+            GossipMessage::Ack(_) => "ack",
+            GossipMessage::Sync { .. } => "sync",
+            GossipMessage::Digest { .. } => "digest",
+            GossipMessage::Delta { .. } => "delta",
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -465,10 +612,12 @@ struct Transport {
 }
 
 impl Transport {
-    pub fn new() -> Self {
-        Transport {
-            client: Client::new(),
-        }
+    pub fn new(timing: &GossipTiming) -> Result<Self, GossipError> {
+        let client = Client::builder()
+            .timeout(timing.request_timeout)
+            .build()
+            .or_raise(|| GossipError("failed to build gossip transport client".to_string()))?;
+        Ok(Transport { client })
     }
 
     pub async fn send(
@@ -478,14 +627,83 @@ impl Transport {
     ) -> Result<GossipMessage, GossipError> {
         let make_error = || GossipError(format!("failed to send message to {url}"));
         let url = url.join("gossip").or_raise(make_error)?;
-        let resp = self
-            .client
-            .post(url)
-            .json(message)
+
+        let mut resp = self.post(&url, GossipCodec::Postcard, message).await?;
+        if resp.status() == StatusCode::UNSUPPORTED_MEDIA_TYPE {
+            // the peer predates the binary codec
+            resp = self.post(&url, GossipCodec::Json, message).await?;
+        }
+        ensure!(resp.status().is_success(), make_error());
+
+        let codec = resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(GossipCodec::from_content_type)
+            .unwrap_or(GossipCodec::Json);
+        let body = resp.bytes().await.or_raise(make_error)?;
+        codec.decode(&body).or_raise(make_error)
+    }
+
+    async fn post(
+        &self,
+        url: &Url,
+        codec: GossipCodec,
+        message: &GossipMessage,
+    ) -> Result<reqwest::Response, GossipError> {
+        let make_error = || GossipError(format!("failed to send message to {url}"));
+        let body = codec.encode(message)?;
+        self.client
+            .post(url.clone())
+            .header(CONTENT_TYPE, codec.content_type())
+            .body(body)
             .send()
             .await
-            .or_raise(make_error)?;
-        ensure!(resp.status().is_success(), make_error());
-        resp.json().await.or_raise(make_error)
+            .or_raise(make_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use percas_core::GossipPreset;
+
+    use super::*;
+
+    fn make_node(id: u64) -> NodeInfo {
+        NodeInfo::new(
+            Uuid::from_u64_pair(0, id),
+            "cluster".to_string(),
+            Url::parse("http://127.0.0.1:7654").unwrap(),
+            Url::parse("http://127.0.0.1:7655").unwrap(),
+        )
+    }
+
+    #[test]
+    fn ring_epoch_follows_membership() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = GossipState::new(
+            make_node(1),
+            vec![],
+            dir.path().to_path_buf(),
+            GossipPreset::Dev.timing(),
+        )
+        .unwrap();
+        assert_eq!(state.ring().epoch, 0);
+
+        // a new member rebuilds the ring right away
+        state.handle_message(GossipMessage::Ping(make_node(2)));
+        let ring = state.ring();
+        assert_eq!(ring.epoch, 1);
+        assert!(!ring.ring.list_vnodes(&Uuid::from_u64_pair(0, 2)).is_empty());
+
+        // a heartbeat alone does not change how keys are routed
+        state.handle_message(GossipMessage::Ping(make_node(2)));
+        assert_eq!(state.ring().epoch, 1);
+        assert_eq!(state.ring().hash, ring.hash);
+
+        // a status change does
+        state.mark_dead(&make_node(2));
+        assert_eq!(state.ring().epoch, 2);
+        assert_ne!(state.ring().hash, ring.hash);
     }
 }
@@ -12,22 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod codec;
 mod gossip;
 mod member;
 mod node;
 mod proxy;
-mod ring;
 
+pub use codec::GossipCodec;
 pub use gossip::GossipFuture;
 pub use gossip::GossipMessage;
 pub use gossip::GossipState;
+pub use gossip::RingSnapshot;
+pub use member::MemberDigest;
 pub use member::MemberState;
 pub use member::MemberStatus;
 pub use member::Membership;
 pub use node::NodeInfo;
+pub use percas_ring::HashRing;
 pub use proxy::Proxy;
 pub use proxy::RouteDest;
-pub use ring::HashRing;
+pub use proxy::lookup_owner;
 
 #[derive(Debug, parse_display::Display)]
 pub struct GossipError(String);
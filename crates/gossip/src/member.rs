@@ -48,6 +48,41 @@ pub struct MemberState {
     pub heartbeat: Timestamp,
 }
 
+/// A compact summary of a member's state, exchanged during anti-entropy so that only the
+/// members whose state differs need to be transferred in full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MemberDigest {
+    pub node_id: Uuid,
+    pub incarnation: u64,
+    pub status: MemberStatus,
+    pub heartbeat: Timestamp,
+}
+
+impl MemberDigest {
+    /// Returns a key ordering observations of the same member from stalest to freshest.
+    ///
+    /// A dead observation wins a tie on incarnation and heartbeat, matching
+    /// [`MemberStatus::downgrade_to`]; otherwise both peers would swap their views.
+    fn freshness(&self) -> (u64, Timestamp, bool) {
+        (
+            self.incarnation,
+            self.heartbeat,
+            self.status == MemberStatus::Dead,
+        )
+    }
+}
+
+impl From<&MemberState> for MemberDigest {
+    fn from(member: &MemberState) -> Self {
+        Self {
+            node_id: member.info.node_id,
+            incarnation: member.info.incarnation,
+            status: member.status,
+            heartbeat: member.heartbeat,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Membership {
     members: BTreeMap<Uuid, MemberState>,
@@ -127,6 +162,42 @@ impl Membership {
         }
     }
 
+    pub fn digests(&self) -> Vec<MemberDigest> {
+        self.members.values().map(MemberDigest::from).collect()
+    }
+
+    /// Compare this membership against the digests of a peer.
+    ///
+    /// Returns the members whose local state is fresher than the peer's (or unknown to the
+    /// peer), and the ids of members whose state the peer holds a fresher view of.
+    pub fn diff(&self, digests: &[MemberDigest]) -> (Vec<MemberState>, Vec<Uuid>) {
+        let remote = digests
+            .iter()
+            .map(|digest| (digest.node_id, digest))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut fresher = vec![];
+        let mut wanted = vec![];
+        for (id, member) in &self.members {
+            let local = MemberDigest::from(member);
+            match remote.get(id) {
+                None => fresher.push(member.clone()),
+                Some(digest) if local.freshness() > digest.freshness() => {
+                    fresher.push(member.clone())
+                }
+                Some(digest) if local.freshness() < digest.freshness() => wanted.push(*id),
+                Some(_) => {}
+            }
+        }
+        for id in remote.keys() {
+            if !self.members.contains_key(id) {
+                wanted.push(*id);
+            }
+        }
+
+        (fresher, wanted)
+    }
+
     pub fn remove_member(&mut self, id: Uuid) {
         log::info!(target: "gossip", "removing member: {id}");
         self.members.remove(&id);
@@ -230,6 +301,61 @@ mod membership_tests {
         assert_eq!(stored.info.incarnation, 2);
         assert_eq!(stored.status, MemberStatus::Dead);
     }
+
+    #[test]
+    fn diff_against_digests() {
+        let t0 = Timestamp::constant(100, 0);
+        let t1 = Timestamp::constant(200, 0);
+        let member =
+            |id: u64, incarnation: u64, status: MemberStatus, heartbeat: Timestamp| MemberState {
+                info: NodeInfo {
+                    incarnation,
+                    ..make_node(Uuid::from_u64_pair(0, id), 0)
+                },
+                status,
+                heartbeat,
+            };
+
+        let local = Membership::from_iter([
+            member(1, 0, MemberStatus::Alive, t0), // in sync
+            member(2, 0, MemberStatus::Alive, t1), // fresher heartbeat locally
+            member(3, 1, MemberStatus::Alive, t0), // fresher incarnation remotely
+            member(4, 0, MemberStatus::Alive, t0), // same heartbeat; dead remotely
+            member(5, 0, MemberStatus::Alive, t0), // unknown remotely
+        ]);
+        let remote = Membership::from_iter([
+            member(1, 0, MemberStatus::Alive, t0),
+            member(2, 0, MemberStatus::Alive, t0),
+            member(3, 2, MemberStatus::Alive, t0),
+            member(4, 0, MemberStatus::Dead, t0),
+            member(6, 0, MemberStatus::Alive, t0), // unknown locally
+        ]);
+
+        let (fresher, wanted) = local.diff(&remote.digests());
+        let fresher = fresher
+            .iter()
+            .map(|m| m.info.node_id.as_u64_pair().1)
+            .collect::<Vec<_>>();
+        let wanted = wanted
+            .iter()
+            .map(|id| id.as_u64_pair().1)
+            .collect::<Vec<_>>();
+        assert_eq!(fresher, vec![2, 5]);
+        assert_eq!(wanted, vec![3, 4, 6]);
+
+        // applying the exchange in both directions converges the two views
+        let (to_remote, _) = local.diff(&remote.digests());
+        let (to_local, _) = remote.diff(&local.digests());
+        let mut local = local;
+        let mut remote = remote;
+        to_local.into_iter().for_each(|m| {
+            local.update_member(m);
+        });
+        to_remote.into_iter().for_each(|m| {
+            remote.update_member(m);
+        });
+        assert_eq!(local.digests(), remote.digests());
+    }
 }
 
 #[cfg(test)]
@@ -15,11 +15,16 @@
 use std::io;
 use std::path::Path;
 
+use exn::Result;
+use exn::ResultExt;
+use exn::bail;
 use reqwest::Url;
 use serde::Deserialize;
 use serde::Serialize;
 use uuid::Uuid;
 
+use crate::GossipError;
+
 /// PersistentNodeInfo is used to store the node information in a file.
 ///
 /// The `advertise_data_url` and `advertise_ctrl_url` fields are not included in this struct,
@@ -42,7 +47,7 @@ impl From<NodeInfo> for PersistentNodeInfo {
 }
 
 impl PersistentNodeInfo {
-    fn load(path: &Path) -> Result<Option<Self>, io::Error> {
+    fn load(path: &Path) -> std::result::Result<Option<Self>, io::Error> {
         if path.exists() {
             let data = std::fs::read_to_string(path)?;
             let info = serde_json::from_str(&data)?;
@@ -52,7 +57,7 @@ impl PersistentNodeInfo {
         }
     }
 
-    fn persist(&self, path: &Path) -> Result<(), io::Error> {
+    fn persist(&self, path: &Path) -> std::result::Result<(), io::Error> {
         let data = serde_json::to_string_pretty(self)?;
         std::fs::write(path, data)
     }
@@ -87,21 +92,27 @@ impl NodeInfo {
         self.incarnation += 1;
     }
 
-    pub fn load(path: &Path, data_url: Url, ctrl_url: Url) -> Option<Self> {
-        let info = PersistentNodeInfo::load(path).unwrap_or_else(|err| {
-            panic!(
-                "unrecoverable: failed to load node info from {}: {err}",
-                path.display()
-            )
-        });
-
-        info.map(|info| Self {
+    /// Load the node info persisted at `path`, which must belong to the cluster `cluster_id`.
+    pub fn load(
+        path: &Path,
+        cluster_id: &str,
+        data_url: Url,
+        ctrl_url: Url,
+    ) -> Result<Option<Self>, GossipError> {
+        let info = load_checked(path, cluster_id)?;
+        Ok(info.map(|info| Self {
             node_id: info.node_id,
             cluster_id: info.cluster_id,
             advertise_data_url: data_url,
             advertise_ctrl_url: ctrl_url,
             incarnation: info.incarnation,
-        })
+        }))
+    }
+
+    /// Load only the node id persisted at `path`, which must belong to the cluster `cluster_id`.
+    pub fn load_id(path: &Path, cluster_id: &str) -> Result<Option<Uuid>, GossipError> {
+        let info = load_checked(path, cluster_id)?;
+        Ok(info.map(|info| info.node_id))
     }
 
     pub fn persist(&self, path: &Path) {
@@ -119,3 +130,22 @@ impl NodeInfo {
         })
     }
 }
+
+fn load_checked(path: &Path, cluster_id: &str) -> Result<Option<PersistentNodeInfo>, GossipError> {
+    let info = PersistentNodeInfo::load(path).or_raise(|| {
+        GossipError::new(format!("failed to load node info from {}", path.display()))
+    })?;
+
+    if let Some(info) = &info
+        && info.cluster_id != cluster_id
+    {
+        bail!(GossipError::new(format!(
+            "node {} in {} belongs to cluster {:?}, but the configured cluster is {cluster_id:?}; \
+             remove the file to join this cluster as a new node",
+            info.node_id,
+            path.display(),
+            info.cluster_id,
+        )));
+    }
+    Ok(info)
+}
@@ -14,10 +14,32 @@
 
 use std::sync::Arc;
 
+use percas_ring::HashRing;
 use reqwest::Url;
+use uuid::Uuid;
 
 use crate::gossip::GossipState;
+use crate::gossip::RingSnapshot;
+use crate::member::MemberState;
 use crate::member::MemberStatus;
+use crate::member::Membership;
+
+/// Lookups the member owning the key: the first alive member at or after the key on the ring.
+///
+/// Clients route keys the same way from the members and virtual nodes reported by a node.
+pub fn lookup_owner<'a>(
+    ring: &HashRing<Uuid>,
+    membership: &'a Membership,
+    key: &str,
+) -> Option<&'a MemberState> {
+    let members = membership.members();
+    let id = ring.lookup_until(key, |id| {
+        members
+            .get(id)
+            .is_some_and(|member| member.status == MemberStatus::Alive)
+    })?;
+    members.get(&id)
+}
 
 #[derive(Debug, Clone)]
 pub enum RouteDest {
@@ -35,29 +57,22 @@ impl Proxy {
         Self { gossip }
     }
 
-    pub fn route(&self, key: &str) -> RouteDest {
-        let ring = self.gossip.ring();
+    /// The ring snapshot that keys are currently routed with.
+    pub fn ring(&self) -> Arc<RingSnapshot> {
+        self.gossip.ring()
+    }
 
+    pub fn route(&self, key: &str) -> RouteDest {
+        let snapshot = self.gossip.ring();
+        let ring = &snapshot.ring;
         let membership = self.gossip.membership();
-        let members = membership.members();
 
-        if let Some(id) = ring.lookup_until(key, |id| {
-            if let Some(member) = members.get(id)
-                && member.status == MemberStatus::Alive
-            {
-                return true;
+        if let Some(target) = lookup_owner(ring, &membership, key) {
+            if target.info.node_id == self.gossip.current().node_id {
+                return RouteDest::Local;
             }
-            false
-        }) {
-            if let Some(target) = members.get(&id) {
-                if target.info.node_id == self.gossip.current().node_id {
-                    return RouteDest::Local;
-                }
 
-                RouteDest::RemoteAddr(target.info.advertise_data_url.clone())
-            } else {
-                RouteDest::Local
-            }
+            RouteDest::RemoteAddr(target.info.advertise_data_url.clone())
         } else {
             log::debug!("no target found for key: [{key}], current ring: {ring:#?}");
             RouteDest::Local
@@ -20,6 +20,11 @@ use opentelemetry::metrics::Gauge;
 use opentelemetry::metrics::Histogram;
 use opentelemetry::metrics::Meter;
 
+/// The tier label of values served from or evicted out of memory.
+pub const TIER_MEMORY: &str = "memory";
+/// The tier label of values served from or evicted out of disk.
+pub const TIER_DISK: &str = "disk";
+
 pub struct GlobalMetrics {
     pub meter: Meter,
     pub storage: StorageMetrics,
@@ -45,6 +50,13 @@ impl GlobalMetrics {
 pub struct StorageMetrics {
     pub capacity: Gauge<u64>,
     pub used: Gauge<u64>,
+    pub memory_capacity: Gauge<u64>,
+    pub memory_used: Gauge<u64>,
+    pub memory_entries: Gauge<u64>,
+    pub blocks: Gauge<u64>,
+    pub block_fill_ratio: Gauge<f64>,
+    pub flush_dropped: Counter<u64>,
+    pub evictions: Counter<u64>,
     pub io: StorageIOMetrics,
 }
 
@@ -58,13 +70,72 @@ impl StorageMetrics {
                 .build(),
             used: meter
                 .u64_gauge("percas.storage.used")
-                .with_description("The used capacity of the storage")
+                .with_description(
+                    "The bytes of disk blocks allocated to entries or being written, \
+                     including space not yet reclaimed from evicted or deleted entries",
+                )
+                .with_unit("byte")
+                .build(),
+            memory_capacity: meter
+                .u64_gauge("percas.storage.memory.capacity")
+                .with_description("The capacity of the memory tier")
                 .with_unit("byte")
                 .build(),
+            memory_used: meter
+                .u64_gauge("percas.storage.memory.used")
+                .with_description("The bytes of entries in the memory tier")
+                .with_unit("byte")
+                .build(),
+            memory_entries: meter
+                .u64_gauge("percas.storage.memory.entries")
+                .with_description("The number of entries in the memory tier")
+                .build(),
+            blocks: meter
+                .u64_gauge("percas.storage.blocks")
+                .with_description("The number of disk blocks by state")
+                .build(),
+            block_fill_ratio: meter
+                .f64_gauge("percas.storage.block.fill_ratio")
+                .with_description("The share of disk blocks that hold entries or are being written")
+                .build(),
+            flush_dropped: meter
+                .u64_counter("percas.storage.flush.dropped")
+                .with_description("The number of entries dropped because flushing to disk lagged")
+                .build(),
+            evictions: meter
+                .u64_counter("percas.storage.evictions")
+                .with_description("The number of entries or blocks leaving a tier, by reason")
+                .build(),
 
             io: StorageIOMetrics::new(meter),
         }
     }
+
+    pub const BLOCK_CLEAN: &str = "clean";
+    pub const BLOCK_WRITING: &str = "writing";
+    pub const BLOCK_EVICTABLE: &str = "evictable";
+    pub const BLOCK_RECLAIMING: &str = "reclaiming";
+
+    pub fn block_state_labels(state: &str) -> [KeyValue; 1] {
+        [KeyValue::new("state", state.to_string())]
+    }
+
+    pub const TIER_MEMORY: &str = TIER_MEMORY;
+    pub const TIER_DISK: &str = TIER_DISK;
+
+    /// Evicted to make room; from memory, entries move to disk.
+    pub const EVICTION_CAPACITY: &str = "capacity";
+    /// Overwritten by a newer value of the same key.
+    pub const EVICTION_REPLACE: &str = "replace";
+    /// Deleted.
+    pub const EVICTION_REMOVE: &str = "remove";
+
+    pub fn eviction_labels(tier: &str, reason: &str) -> [KeyValue; 2] {
+        [
+            KeyValue::new("tier", tier.to_string()),
+            KeyValue::new("reason", reason.to_string()),
+        ]
+    }
 }
 
 pub struct StorageIOMetrics {
@@ -100,6 +171,7 @@ pub struct OperationMetrics {
     pub count: Counter<u64>,
     pub bytes: Counter<u64>,
     pub duration: Histogram<f64>,
+    pub value_size: Histogram<u64>,
 }
 
 impl OperationMetrics {
@@ -125,6 +197,18 @@ impl OperationMetrics {
                     .into(),
                 )
                 .build(),
+            value_size: meter
+                .u64_histogram("percas.operation.value_size")
+                .with_description("The size of the values read or written")
+                .with_unit("byte")
+                .with_boundaries(
+                    [
+                        64.0, 256.0, 1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0,
+                        4194304.0, 16777216.0,
+                    ]
+                    .into(),
+                )
+                .build(),
         }
     }
 
@@ -138,10 +222,22 @@ impl OperationMetrics {
     pub const STATUS_FAILURE: &str = "error";
     pub const STATUS_REDIRECT: &str = "redirect";
 
+    pub const TIER_MEMORY: &str = TIER_MEMORY;
+    pub const TIER_DISK: &str = TIER_DISK;
+
     pub fn operation_labels(operation: &str, status: &str) -> [KeyValue; 2] {
         [
             KeyValue::new("operation", operation.to_string()),
             KeyValue::new("status", status.to_string()),
         ]
     }
+
+    /// Labels of a get that found its value in `tier`.
+    pub fn hit_labels(tier: &str) -> [KeyValue; 3] {
+        [
+            KeyValue::new("operation", Self::OPERATION_GET),
+            KeyValue::new("status", Self::STATUS_SUCCESS),
+            KeyValue::new("tier", tier.to_string()),
+        ]
+    }
 }
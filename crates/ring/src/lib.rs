@@ -12,6 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! The consistent hash ring that Percas nodes and clients route keys with.
+//!
+//! Nodes build the ring from the cluster membership and report the virtual nodes of each member,
+//! from which clients build the same ring, so that both sides agree on the owner of every key.
+
+#![deny(missing_docs)]
+
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::fmt::Debug;
@@ -27,13 +34,14 @@ const DEFAULT_VNODE_COUNT: u32 = 64;
 /// # Examples
 ///
 /// ```
-/// use percas_gossip::HashRing;
+/// use percas_ring::HashRing;
 ///
 /// let ring = HashRing::from(["node-1", "node-2", "node-3"]);
 /// assert_eq!(ring.lookup("key1"), Some("node-2"));
 /// assert_eq!(ring.lookup("key2"), Some("node-3"));
 /// assert_eq!(ring.lookup("key3"), Some("node-2"));
 /// ```
+#[derive(Clone)]
 pub struct HashRing<T> {
     vnodes: u32,
     nodes: BTreeMap<u32, BTreeSet<T>>,
@@ -134,8 +142,19 @@ where
     /// Adds a node to the ring.
     /// The node will be replicated `replica_count` times in the ring.
     pub fn add_node(&mut self, node: T) {
-        for i in 0..self.vnodes {
-            let hash = self.hash_node(&node, i);
+        let vnodes = self.list_vnodes(&node);
+        self.add_node_with_vnodes(node, vnodes);
+    }
+
+    /// Adds a node to the ring at the given virtual nodes (hashes).
+    ///
+    /// This rebuilds a ring from the virtual nodes listed by [`HashRing::list_vnodes`] of another
+    /// ring, e.g. on a client from the members reported by a node.
+    pub fn add_node_with_vnodes<I>(&mut self, node: T, vnodes: I)
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        for hash in vnodes {
             self.nodes.entry(hash).or_default().insert(node.clone());
         }
     }
@@ -0,0 +1,189 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use exn::Result;
+use exn::ResultExt;
+use logforth::Append;
+use logforth::Diagnostic;
+use logforth::Layout;
+use logforth::append::file::File;
+use logforth::append::file::FileBuilder;
+use logforth::record::Record;
+use percas_core::AccessLogConfig;
+use serde::Serialize;
+
+use crate::ServerError;
+
+/// A dedicated sink for per-request access records, kept apart from the application logs.
+#[derive(Debug)]
+pub struct AccessLog {
+    file: File,
+    sample_one_in: u64,
+    hash_keys: bool,
+    requests: AtomicU64,
+}
+
+impl AccessLog {
+    pub fn new(config: &AccessLogConfig, hash_keys: bool) -> Result<Self, ServerError> {
+        let mut builder = FileBuilder::new(&config.dir, "access")
+            .layout(PayloadLayout)
+            .rollover_hourly()
+            .filename_suffix("log");
+        if let Some(max_files) = config.max_files {
+            builder = builder.max_log_files(max_files);
+        }
+        let file = builder
+            .build()
+            .or_raise(|| ServerError(format!("failed to open access log under {}", config.dir)))?;
+
+        Ok(Self {
+            file,
+            sample_one_in: config.sample_one_in.map_or(1, |n| n.get() as u64),
+            hash_keys,
+            requests: AtomicU64::new(0),
+        })
+    }
+
+    /// Whether to record the next request; server errors are always recorded.
+    pub(crate) fn sample(&self, server_error: bool) -> bool {
+        let n = self.requests.fetch_add(1, Ordering::Relaxed);
+        server_error || n.is_multiple_of(self.sample_one_in)
+    }
+
+    pub(crate) fn record(&self, mut entry: AccessLogEntry<'_>) {
+        if self.hash_keys
+            && let Some(key) = entry.key.take()
+        {
+            entry.key_hash = Some(hash_key(key));
+        }
+
+        let payload = match serde_json::to_string(&entry) {
+            Ok(payload) => payload,
+            Err(err) => {
+                log::warn!("failed to encode access log entry: {err}");
+                return;
+            }
+        };
+        let record = Record::builder().payload(payload).build();
+        if let Err(err) = self.file.append(&record, &[]) {
+            log::warn!("failed to write access log: {err}");
+        }
+    }
+}
+
+/// The hash that stands for a key in the access log, the application logs and the hot key
+/// reports when `telemetry.diagnostics.hash_keys` is set.
+pub fn hash_key(key: &str) -> String {
+    let (hi, lo) = mur3::murmurhash3_x64_128(key.as_bytes(), 0);
+    format!("{hi:016x}{lo:016x}")
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct AccessLogEntry<'a> {
+    pub ts: String,
+    pub client_addr: String,
+    pub method: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_hash: Option<String>,
+    pub status: u16,
+    pub bytes_in: Option<u64>,
+    pub bytes_out: Option<u64>,
+    /// The codec a typed client named for the value it writes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+    pub latency_ms: f64,
+    pub route: Option<&'static str>,
+    pub trace_id: Option<String>,
+}
+
+/// Write the pre-encoded JSON payload as is.
+#[derive(Debug)]
+struct PayloadLayout;
+
+impl Layout for PayloadLayout {
+    fn format(
+        &self,
+        record: &Record,
+        _: &[Box<dyn Diagnostic>],
+    ) -> std::result::Result<Vec<u8>, logforth::Error> {
+        Ok(record.payload().as_bytes().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::*;
+
+    fn entry(key: &str, status: u16) -> AccessLogEntry<'_> {
+        AccessLogEntry {
+            ts: "2025-01-01T00:00:00Z".to_string(),
+            client_addr: "127.0.0.1:1234".to_string(),
+            method: "GET",
+            key: Some(key),
+            key_hash: None,
+            status,
+            bytes_in: None,
+            bytes_out: Some(3),
+            codec: None,
+            latency_ms: 1.5,
+            route: Some("local"),
+            trace_id: None,
+        }
+    }
+
+    #[test]
+    fn test_sample_and_hash_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = AccessLogConfig {
+            dir: dir.path().display().to_string(),
+            max_files: None,
+            sample_one_in: NonZeroU32::new(2),
+        };
+        let access_log = AccessLog::new(&config, true).unwrap();
+        for (key, status) in [("a", 200), ("b", 500), ("c", 200), ("d", 200)] {
+            if access_log.sample(status >= 500) {
+                let codec = (key == "c").then(|| "json+zstd".to_string());
+                access_log.record(AccessLogEntry {
+                    codec,
+                    ..entry(key, status)
+                });
+            }
+        }
+        drop(access_log);
+
+        let files = std::fs::read_dir(dir.path()).unwrap().collect::<Vec<_>>();
+        assert_eq!(files.len(), 1);
+        let content = std::fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+        let lines = content
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        // "a" and "c" are sampled, "b" is recorded as a server error
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["status"], 500);
+        assert!(lines[1].get("codec").is_none());
+        assert_eq!(lines[2]["codec"], "json+zstd");
+        for line in &lines {
+            assert!(line.get("key").is_none());
+            assert_eq!(line["key_hash"].as_str().unwrap().len(), 32);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fencing of the directories and devices a node owns, so that two processes never share them.
+
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::fs::TryLockError;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use exn::Result;
+use exn::ResultExt;
+use exn::bail;
+use percas_core::DeviceKind;
+use percas_core::StorageConfig;
+use percas_core::StorageEngine;
+use serde::Deserialize;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::ServerError;
+
+const LOCK_FILE_NAME: &str = "percas.lock";
+const MARKER_FILE_NAME: &str = "storage.json";
+
+/// The marker in a storage directory that records the node it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct StorageMarker {
+    node_id: Uuid,
+}
+
+/// Exclusive locks held on the directories and devices of a node until it is dropped.
+#[derive(Debug, Default)]
+pub struct Fence {
+    locks: Vec<(PathBuf, File)>,
+}
+
+impl Fence {
+    /// Lock the node directory `dir`, creating it if missing.
+    pub fn lock_node_dir(dir: &Path) -> Result<Self, ServerError> {
+        std::fs::create_dir_all(dir)
+            .or_raise(|| ServerError(format!("failed to create node dir: {}", dir.display())))?;
+
+        let mut fence = Fence::default();
+        fence.lock(&dir.join(LOCK_FILE_NAME))?;
+        Ok(fence)
+    }
+
+    /// Lock the data devices of `storage` and check that they belong to the node `node_id`.
+    ///
+    /// A directory without a marker is claimed by writing one. Block devices are locked but carry
+    /// no marker.
+    pub fn claim_storage(
+        &mut self,
+        storage: &StorageConfig,
+        node_id: Uuid,
+    ) -> Result<(), ServerError> {
+        if storage.data_engine() == StorageEngine::Memory {
+            return Ok(());
+        }
+
+        for device in storage.devices() {
+            match device.kind {
+                DeviceKind::Fs => {
+                    let dir = device.path.as_path();
+                    std::fs::create_dir_all(dir).or_raise(|| {
+                        ServerError(format!("failed to create data dir: {}", dir.display()))
+                    })?;
+                    self.lock(&dir.join(LOCK_FILE_NAME))?;
+                    claim_dir(dir, node_id)?;
+                }
+                DeviceKind::Block => self.lock(&device.path)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Lock the data devices of `storage` and remove their markers, so that they can be claimed
+    /// by a node with a new identity. Their data is kept.
+    pub fn release_storage(&mut self, storage: &StorageConfig) -> Result<(), ServerError> {
+        if storage.data_engine() == StorageEngine::Memory {
+            return Ok(());
+        }
+
+        for device in storage.devices() {
+            let dir = device.path.as_path();
+            if device.kind != DeviceKind::Fs || !dir.exists() {
+                continue;
+            }
+
+            self.lock(&dir.join(LOCK_FILE_NAME))?;
+            let path = dir.join(MARKER_FILE_NAME);
+            if path.exists() {
+                std::fs::remove_file(&path).or_raise(|| {
+                    ServerError(format!("failed to release data dir: {}", dir.display()))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn lock(&mut self, path: &Path) -> Result<(), ServerError> {
+        let make_error = || ServerError(format!("failed to lock {}", path.display()));
+        let is_file = !path.exists() || path.is_file();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(is_file)
+            .truncate(false)
+            .open(path)
+            .or_raise(make_error)?;
+
+        // locks of one process conflict with each other, so a path shared by two roles is only
+        // locked once
+        let canonical = path.canonicalize().or_raise(make_error)?;
+        if self.locks.iter().any(|(p, _)| *p == canonical) {
+            return Ok(());
+        }
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let holder = if is_file {
+                    std::fs::read_to_string(path)
+                        .ok()
+                        .map(|pid| format!(" (pid {})", pid.trim()))
+                        .unwrap_or_default()
+                } else {
+                    String::new()
+                };
+                bail!(ServerError(format!(
+                    "{} is locked by another percas process{holder}; \
+                     each node needs its own server.dir and storage devices",
+                    path.display()
+                )));
+            }
+            Err(TryLockError::Error(err)) => return Err(err).or_raise(make_error),
+        }
+
+        // record the holder for operators; block devices are never written here
+        if is_file {
+            file.set_len(0).or_raise(make_error)?;
+            write!(file, "{}", std::process::id()).or_raise(make_error)?;
+        }
+
+        self.locks.push((canonical, file));
+        Ok(())
+    }
+}
+
+fn claim_dir(dir: &Path, node_id: Uuid) -> Result<(), ServerError> {
+    let path = dir.join(MARKER_FILE_NAME);
+    let make_error = || ServerError(format!("failed to claim data dir: {}", dir.display()));
+
+    if path.exists() {
+        let data = std::fs::read_to_string(&path).or_raise(make_error)?;
+        let marker: StorageMarker = serde_json::from_str(&data).or_raise(make_error)?;
+        if marker.node_id != node_id {
+            bail!(ServerError(format!(
+                "data dir {} belongs to node {}, but this is node {node_id}; \
+                 remove {} to discard its data and reuse the dir",
+                dir.display(),
+                marker.node_id,
+                path.display()
+            )));
+        }
+    } else {
+        let marker = StorageMarker { node_id };
+        let data = serde_json::to_string_pretty(&marker).or_raise(make_error)?;
+        std::fs::write(&path, data).or_raise(make_error)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_storage(data_dir: &Path) -> StorageConfig {
+        StorageConfig {
+            data_dir: data_dir.to_path_buf(),
+            ..percas_core::Config::default().storage
+        }
+    }
+
+    #[test]
+    fn test_lock_node_dir_once() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let fence = Fence::lock_node_dir(dir.path()).unwrap();
+        let err = Fence::lock_node_dir(dir.path()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("is locked by another percas process")
+        );
+
+        drop(fence);
+        Fence::lock_node_dir(dir.path()).unwrap();
+    }
+
+    #[test]
+    fn test_claim_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = make_storage(dir.path());
+        let node_id = Uuid::now_v7();
+
+        // the node dir may double as the data dir
+        let mut fence = Fence::lock_node_dir(dir.path()).unwrap();
+        fence.claim_storage(&storage, node_id).unwrap();
+        drop(fence);
+
+        Fence::default().claim_storage(&storage, node_id).unwrap();
+        let err = Fence::default()
+            .claim_storage(&storage, Uuid::now_v7())
+            .unwrap_err();
+        assert!(err.to_string().contains("belongs to node"));
+
+        Fence::default().release_storage(&storage).unwrap();
+        Fence::default()
+            .claim_storage(&storage, Uuid::now_v7())
+            .unwrap();
+    }
+}
@@ -0,0 +1,250 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::access_log::hash_key;
+
+/// How many rows of counters the sketch has; each row bounds the error independently.
+const DEPTH: usize = 4;
+
+/// How many counters each row has per reported key, which bounds the error of the reported counts.
+const WIDTH_FACTOR: usize = 64;
+
+/// Once the top-K is full, a key is only checked against it on every this many requests.
+const SAMPLE_ONE_IN: u64 = 16;
+
+/// Approximate top-K of the keys requested on this node, kept by a count-min sketch.
+///
+/// Every request bumps `DEPTH` atomic counters picked by the hash of its key and takes no lock.
+/// Only a key whose estimate exceeds the least counted key of the top-K, sampled once in
+/// `SAMPLE_ONE_IN` requests, is checked against the top-K under its lock. An estimate never
+/// underestimates the true count and overestimates it by at most its `error` with high
+/// probability.
+#[derive(Debug)]
+pub struct HotKeys {
+    top_k: usize,
+    hash_keys: bool,
+    width: usize,
+    counters: Box<[AtomicU64]>,
+    total: AtomicU64,
+    /// The least estimate in the top-K once it is full, or 0 while it has room.
+    threshold: AtomicU64,
+    candidates: Mutex<Vec<Candidate>>,
+}
+
+#[derive(Debug)]
+struct Candidate {
+    hash: (u64, u64),
+    key: String,
+    count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotKey {
+    pub key: String,
+    pub count: u64,
+    pub error: u64,
+}
+
+impl HotKeys {
+    /// Create a tracker that reports the `top_k` most requested keys; 0 disables it.
+    ///
+    /// With `hash_keys`, keys are tracked and reported by their hash only.
+    pub fn new(top_k: usize, hash_keys: bool) -> Self {
+        let width = if top_k > 0 {
+            (top_k * WIDTH_FACTOR).next_power_of_two()
+        } else {
+            0
+        };
+        let counters = (0..DEPTH * width).map(|_| AtomicU64::new(0)).collect();
+        Self {
+            top_k,
+            hash_keys,
+            width,
+            counters,
+            total: AtomicU64::new(0),
+            threshold: AtomicU64::new(0),
+            candidates: Mutex::new(Vec::with_capacity(top_k)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.top_k > 0
+    }
+
+    pub fn record(&self, key: &str) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let hash = mur3::murmurhash3_x64_128(key.as_bytes(), 0);
+        self.total.fetch_add(1, Ordering::Relaxed);
+        let estimate = (0..DEPTH)
+            .map(|row| self.counters[self.slot(hash, row)].fetch_add(1, Ordering::Relaxed) + 1)
+            .min()
+            .expect("sketch must have rows");
+
+        let threshold = self.threshold.load(Ordering::Relaxed);
+        if estimate <= threshold || (threshold > 0 && !estimate.is_multiple_of(SAMPLE_ONE_IN)) {
+            return;
+        }
+
+        let mut candidates = self.candidates.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(candidate) = candidates.iter_mut().find(|c| c.hash == hash) {
+            candidate.count = estimate;
+        } else {
+            let candidate = Candidate {
+                hash,
+                key: if self.hash_keys {
+                    hash_key(key)
+                } else {
+                    key.to_string()
+                },
+                count: estimate,
+            };
+            if candidates.len() < self.top_k {
+                candidates.push(candidate);
+            } else {
+                // replace the least counted key if this one has overtaken it
+                let min = candidates
+                    .iter_mut()
+                    .min_by_key(|c| c.count)
+                    .expect("candidates must be full");
+                if min.count < estimate {
+                    *min = candidate;
+                }
+            }
+        }
+        self.update_threshold(&candidates);
+    }
+
+    /// The most requested keys, most requested first.
+    pub fn top(&self) -> Vec<HotKey> {
+        if !self.is_enabled() {
+            return vec![];
+        }
+
+        // with probability 1 - e^-DEPTH, an estimate exceeds the true count by at most e/width of
+        // all requests
+        let total = self.total.load(Ordering::Relaxed);
+        let max_error = (total as f64 * std::f64::consts::E / self.width as f64).ceil() as u64;
+
+        let candidates = self.candidates.lock().unwrap_or_else(|e| e.into_inner());
+        let mut keys = candidates
+            .iter()
+            .map(|candidate| {
+                let count = self.estimate(candidate.hash);
+                HotKey {
+                    key: candidate.key.clone(),
+                    count,
+                    error: max_error.min(count),
+                }
+            })
+            .filter(|key| key.count > 0)
+            .collect::<Vec<_>>();
+        drop(candidates);
+
+        keys.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+        keys
+    }
+
+    /// Halve all counts so that the tracker follows recent traffic.
+    pub fn decay(&self) {
+        let halve = |counter: &AtomicU64| {
+            let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| Some(n / 2));
+        };
+        self.counters.iter().for_each(halve);
+        halve(&self.total);
+
+        let mut candidates = self.candidates.lock().unwrap_or_else(|e| e.into_inner());
+        for candidate in candidates.iter_mut() {
+            candidate.count = self.estimate(candidate.hash);
+        }
+        candidates.retain(|candidate| candidate.count > 0);
+        self.update_threshold(&candidates);
+    }
+
+    /// The counter of the given row for a key hash, picked by double hashing.
+    fn slot(&self, (h1, h2): (u64, u64), row: usize) -> usize {
+        let index = h1.wrapping_add((row as u64).wrapping_mul(h2)) as usize & (self.width - 1);
+        row * self.width + index
+    }
+
+    fn estimate(&self, hash: (u64, u64)) -> u64 {
+        (0..DEPTH)
+            .map(|row| self.counters[self.slot(hash, row)].load(Ordering::Relaxed))
+            .min()
+            .expect("sketch must have rows")
+    }
+
+    fn update_threshold(&self, candidates: &[Candidate]) {
+        let threshold = if candidates.len() < self.top_k {
+            0
+        } else {
+            candidates.iter().map(|c| c.count).min().unwrap_or(0)
+        };
+        self.threshold.store(threshold, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hot_keys() {
+        let hot_keys = HotKeys::new(2, false);
+        for i in 0..1000 {
+            hot_keys.record("hot");
+            if i % 2 == 0 {
+                hot_keys.record("warm");
+            }
+            hot_keys.record(&format!("cold-{i}"));
+        }
+
+        let top = hot_keys.top();
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].key, "hot");
+        assert!((1000..=1000 + top[0].error).contains(&top[0].count));
+        assert_eq!(top[1].key, "warm");
+        assert!((500..=500 + top[1].error).contains(&top[1].count));
+
+        hot_keys.decay();
+        let top = hot_keys.top();
+        assert!((500..=500 + top[0].error).contains(&top[0].count));
+        assert!((250..=250 + top[1].error).contains(&top[1].count));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let hot_keys = HotKeys::new(0, false);
+        hot_keys.record("foo");
+        assert!(hot_keys.top().is_empty());
+    }
+
+    #[test]
+    fn test_hash_keys() {
+        let hot_keys = HotKeys::new(1, true);
+        hot_keys.record("secret");
+        let top = hot_keys.top();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].key, hash_key("secret"));
+    }
+}
@@ -12,18 +12,44 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use percas_core::Engine;
+
+use crate::access_log::hash_key;
+use crate::hotkeys::HotKeys;
+
+pub mod access_log;
+pub mod fence;
+pub mod hotkeys;
 pub mod middleware;
 pub mod scheduled;
 pub mod server;
 pub mod telemetry;
 
 pub struct PercasContext {
-    engine: percas_core::FoyerEngine,
+    engine: Arc<dyn Engine>,
+    hot_keys: HotKeys,
+    hash_keys: bool,
 }
 
 impl PercasContext {
-    pub fn new(engine: percas_core::FoyerEngine) -> Self {
-        Self { engine }
+    pub fn new(engine: Arc<dyn Engine>, hot_keys: HotKeys, hash_keys: bool) -> Self {
+        Self {
+            engine,
+            hot_keys,
+            hash_keys,
+        }
+    }
+
+    /// How a key appears in the logs: its hash when `telemetry.diagnostics.hash_keys` is set.
+    pub(crate) fn log_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        if self.hash_keys {
+            Cow::Owned(hash_key(key))
+        } else {
+            Cow::Borrowed(key)
+        }
     }
 }
 
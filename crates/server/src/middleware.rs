@@ -13,24 +13,56 @@
 // limitations under the License.
 
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 
+use fastrace::Span;
+use fastrace::future::FutureExt;
+use fastrace::local::LocalSpan;
+use fastrace::prelude::SpanContext;
+use http_body::Body as _;
+use http_body_util::combinators::BoxBody;
+use jiff::Timestamp;
 use mea::semaphore::Semaphore;
 use percas_core::num_cpus;
 use percas_gossip::Proxy;
 use percas_gossip::RouteDest;
 use percas_metrics::GlobalMetrics;
 use percas_metrics::OperationMetrics;
+use percent_encoding::percent_decode_str;
 use poem::Endpoint;
 use poem::IntoResponse;
 use poem::Middleware;
 use poem::Request;
 use poem::Response;
+use poem::http::HeaderValue;
 use poem::http::StatusCode;
+use poem::http::header::CONTENT_LENGTH;
+use uuid::Uuid;
 
+use crate::PercasContext;
+use crate::access_log::AccessLog;
+use crate::access_log::AccessLogEntry;
+use crate::access_log::hash_key;
+use crate::server::CODEC_HEADER;
+use crate::server::RING_EPOCH_HEADER;
+use crate::server::RING_HASH_HEADER;
+use crate::server::TRACEPARENT_HEADER;
 use crate::server::temporary_redirect;
 use crate::server::too_many_requests;
 
-pub struct LoggerMiddleware;
+/// Log every request and its outcome.
+///
+/// With `hash_keys`, requests are logged with the hash of their key instead of the URI.
+pub struct LoggerMiddleware {
+    hash_keys: bool,
+}
+
+impl LoggerMiddleware {
+    pub fn new(hash_keys: bool) -> Self {
+        Self { hash_keys }
+    }
+}
 
 impl<E> Middleware<E> for LoggerMiddleware
 where
@@ -40,11 +72,17 @@ where
     type Output = LoggerEndpoint<E>;
 
     fn transform(&self, endpoint: E) -> Self::Output {
-        LoggerEndpoint(endpoint)
+        LoggerEndpoint {
+            hash_keys: self.hash_keys,
+            endpoint,
+        }
     }
 }
 
-pub struct LoggerEndpoint<E>(E);
+pub struct LoggerEndpoint<E> {
+    hash_keys: bool,
+    endpoint: E,
+}
 
 impl<E> Endpoint for LoggerEndpoint<E>
 where
@@ -55,9 +93,16 @@ where
 
     async fn call(&self, req: Request) -> Result<Self::Output, poem::Error> {
         let method = req.method().clone();
-        let uri = req.uri().clone();
+        let uri = if self.hash_keys {
+            let path = req.uri().path();
+            let key =
+                percent_decode_str(path.strip_prefix('/').unwrap_or(path)).decode_utf8_lossy();
+            format!("[{}]", hash_key(&key))
+        } else {
+            req.uri().to_string()
+        };
         log::debug!("{method} {uri} called");
-        let resp = self.0.call(req).await.inspect_err(|err| {
+        let resp = self.endpoint.call(req).await.inspect_err(|err| {
             if err.status() != StatusCode::NOT_FOUND {
                 log::error!("{method} {uri} {}: {err}", err.status());
             }
@@ -68,6 +113,272 @@ where
     }
 }
 
+/// Continue the caller's trace from its `traceparent` header, or start a new one.
+pub struct TracingMiddleware {
+    node_id: Uuid,
+}
+
+impl TracingMiddleware {
+    pub fn new(node_id: Uuid) -> Self {
+        Self { node_id }
+    }
+}
+
+impl<E> Middleware<E> for TracingMiddleware
+where
+    E: Endpoint,
+    E::Output: IntoResponse,
+{
+    type Output = TracingEndpoint<E>;
+
+    fn transform(&self, endpoint: E) -> Self::Output {
+        TracingEndpoint {
+            node_id: self.node_id.to_string(),
+            endpoint,
+        }
+    }
+}
+
+pub struct TracingEndpoint<E> {
+    node_id: String,
+    endpoint: E,
+}
+
+impl<E> Endpoint for TracingEndpoint<E>
+where
+    E: Endpoint,
+    E::Output: IntoResponse,
+{
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output, poem::Error> {
+        let parent = req
+            .header(TRACEPARENT_HEADER)
+            .and_then(SpanContext::decode_w3c_traceparent)
+            .unwrap_or_else(SpanContext::random);
+        let method = req.method().to_string();
+        let node_id = self.node_id.clone();
+        let root = Span::root(format!("{method} /*key"), parent)
+            .with_properties(|| [("http.method", method), ("node_id", node_id)]);
+
+        async move {
+            let result = self
+                .endpoint
+                .call(req)
+                .await
+                .map(IntoResponse::into_response);
+            let status = match &result {
+                Ok(resp) => resp.status(),
+                Err(err) => err.status(),
+            };
+            LocalSpan::add_property(|| ("http.status_code", status.as_u16().to_string()));
+            result
+        }
+        .in_span(root)
+        .await
+    }
+}
+
+/// Record one access log entry per data request, if an access log is configured.
+pub struct AccessLogMiddleware {
+    access_log: Option<Arc<AccessLog>>,
+}
+
+impl AccessLogMiddleware {
+    pub fn new(access_log: Option<Arc<AccessLog>>) -> Self {
+        Self { access_log }
+    }
+}
+
+impl<E> Middleware<E> for AccessLogMiddleware
+where
+    E: Endpoint,
+    E::Output: IntoResponse,
+{
+    type Output = AccessLogEndpoint<E>;
+
+    fn transform(&self, endpoint: E) -> Self::Output {
+        AccessLogEndpoint {
+            access_log: self.access_log.clone(),
+            endpoint,
+        }
+    }
+}
+
+pub struct AccessLogEndpoint<E> {
+    access_log: Option<Arc<AccessLog>>,
+    endpoint: E,
+}
+
+impl<E> Endpoint for AccessLogEndpoint<E>
+where
+    E: Endpoint,
+    E::Output: IntoResponse,
+{
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output, poem::Error> {
+        let Some(access_log) = &self.access_log else {
+            return self
+                .endpoint
+                .call(req)
+                .await
+                .map(IntoResponse::into_response);
+        };
+
+        let start = Instant::now();
+        let ts = Timestamp::now();
+        let client_addr = match req.remote_addr().as_socket_addr() {
+            Some(addr) => addr.to_string(),
+            None => req.remote_addr().to_string(),
+        };
+        let method = req.method().clone();
+        let key = req.path_params::<String>().ok();
+        let bytes_in = req
+            .header(CONTENT_LENGTH)
+            .and_then(|len| len.parse::<u64>().ok());
+        let codec = req.header(CODEC_HEADER).map(str::to_string);
+
+        let mut result = self
+            .endpoint
+            .call(req)
+            .await
+            .map(IntoResponse::into_response);
+        let (status, bytes_out, route) = match &mut result {
+            Ok(resp) => (
+                resp.status(),
+                body_size(resp),
+                resp.extensions().get::<RouteDecision>().copied(),
+            ),
+            Err(err) => (err.status(), None, None),
+        };
+
+        if access_log.sample(status.is_server_error()) {
+            access_log.record(AccessLogEntry {
+                ts: ts.to_string(),
+                client_addr,
+                method: method.as_str(),
+                key: key.as_deref(),
+                key_hash: None,
+                status: status.as_u16(),
+                bytes_in,
+                bytes_out,
+                codec,
+                latency_ms: start.elapsed().as_secs_f64() * 1000.0,
+                route: route.map(RouteDecision::as_str),
+                trace_id: SpanContext::current_local_parent()
+                    .map(|ctx| format!("{:032x}", ctx.trace_id.0)),
+            });
+        }
+        result
+    }
+}
+
+/// Count requested keys for hot key detection and log slow requests.
+pub struct DiagnosticsMiddleware {
+    ctx: Arc<PercasContext>,
+    slow_request_threshold: Duration,
+}
+
+impl DiagnosticsMiddleware {
+    pub fn new(ctx: Arc<PercasContext>, slow_request_threshold: Duration) -> Self {
+        Self {
+            ctx,
+            slow_request_threshold,
+        }
+    }
+}
+
+impl<E> Middleware<E> for DiagnosticsMiddleware
+where
+    E: Endpoint,
+    E::Output: IntoResponse,
+{
+    type Output = DiagnosticsEndpoint<E>;
+
+    fn transform(&self, endpoint: E) -> Self::Output {
+        DiagnosticsEndpoint {
+            ctx: self.ctx.clone(),
+            slow_request_threshold: self.slow_request_threshold,
+            endpoint,
+        }
+    }
+}
+
+pub struct DiagnosticsEndpoint<E> {
+    ctx: Arc<PercasContext>,
+    slow_request_threshold: Duration,
+    endpoint: E,
+}
+
+impl<E> Endpoint for DiagnosticsEndpoint<E>
+where
+    E: Endpoint,
+    E::Output: IntoResponse,
+{
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output, poem::Error> {
+        let start = Instant::now();
+        let method = req.method().clone();
+        let key = req.path_params::<String>().unwrap_or_default();
+
+        let result = self
+            .endpoint
+            .call(req)
+            .await
+            .map(IntoResponse::into_response);
+
+        let (status, route) = match &result {
+            Ok(resp) => (
+                resp.status(),
+                resp.extensions().get::<RouteDecision>().copied(),
+            ),
+            Err(err) => (err.status(), None),
+        };
+        // only count keys this node serves, not those redirected to their owner
+        if route == Some(RouteDecision::Local) {
+            self.ctx.hot_keys.record(&key);
+        }
+
+        let elapsed = start.elapsed();
+        if elapsed >= self.slow_request_threshold {
+            let route = route.map_or("none", RouteDecision::as_str);
+            let key = self.ctx.log_key(&key);
+            log::warn!(
+                "slow request: {method} [{key}] returns {status} in {elapsed:?} (route: {route})"
+            );
+        }
+        result
+    }
+}
+
+/// The exact size of a response body, when it is known without reading it.
+fn body_size(resp: &mut Response) -> Option<u64> {
+    let body: BoxBody<_, _> = resp.take_body().into();
+    let size = body.size_hint().exact();
+    resp.set_body(body);
+    size
+}
+
+/// Where [`ClusterProxyEndpoint`] sent a data request, attached to its response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteDecision {
+    /// Served by this node.
+    Local,
+    /// Redirected to the owner node.
+    Redirect,
+}
+
+impl RouteDecision {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RouteDecision::Local => "local",
+            RouteDecision::Redirect => "redirect",
+        }
+    }
+}
+
 pub struct ClusterProxyMiddleware {
     proxy: Proxy,
 }
@@ -107,12 +418,30 @@ where
 
     async fn call(&self, req: Request) -> Result<Self::Output, poem::Error> {
         let key = req.path_params::<String>()?;
-        match self.proxy.route(&key) {
-            RouteDest::Local => self
-                .endpoint
-                .call(req)
-                .await
-                .map(IntoResponse::into_response),
+        let ring = self.proxy.ring();
+        let route = {
+            let _span = LocalSpan::enter_with_local_parent("route").with_properties(|| {
+                [
+                    ("key_size", key.len().to_string()),
+                    ("ring.epoch", ring.epoch.to_string()),
+                    ("ring.hash", ring.hash.to_string()),
+                ]
+            });
+            let route = self.proxy.route(&key);
+            LocalSpan::add_property(|| match &route {
+                RouteDest::Local => ("route.dest", "local"),
+                RouteDest::RemoteAddr(_) => ("route.dest", "remote"),
+            });
+            route
+        };
+        let (decision, mut resp) = match route {
+            RouteDest::Local => (
+                RouteDecision::Local,
+                self.endpoint
+                    .call(req)
+                    .await
+                    .map(IntoResponse::into_response)?,
+            ),
             RouteDest::RemoteAddr(mut url) => {
                 let operation = match req.method().as_str() {
                     "GET" => OperationMetrics::OPERATION_GET,
@@ -130,9 +459,18 @@ where
                 );
 
                 url.set_path(req.uri().path());
-                Ok(temporary_redirect(url.as_ref()))
+                let _span = LocalSpan::enter_with_local_parent("redirect")
+                    .with_property(|| ("http.location", url.to_string()));
+                (RouteDecision::Redirect, temporary_redirect(url.as_ref()))
             }
-        }
+        };
+        resp.extensions_mut().insert(decision);
+
+        // Let clients tell whether their route table is stale
+        let headers = resp.headers_mut();
+        headers.insert(RING_EPOCH_HEADER, HeaderValue::from(ring.epoch));
+        headers.insert(RING_HASH_HEADER, HeaderValue::from(ring.hash));
+        Ok(resp)
     }
 }
 
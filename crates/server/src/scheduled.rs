@@ -15,8 +15,11 @@
 use std::sync::Arc;
 
 use arc_swap::ArcSwap;
+use percas_core::EngineStatistics;
+use percas_core::MemoryEvictions;
 use percas_metrics::GlobalMetrics;
 use percas_metrics::StorageIOMetrics;
+use percas_metrics::StorageMetrics;
 
 use crate::PercasContext;
 
@@ -26,15 +29,21 @@ struct MetricsSnapshot {
     disk_write_bytes: u64,
     disk_read_ios: u64,
     disk_write_ios: u64,
+    flush_dropped_entries: u64,
+    memory_evictions: MemoryEvictions,
+    disk_reclaimed_blocks: u64,
 }
 
-impl From<&foyer::Statistics> for MetricsSnapshot {
-    fn from(stats: &foyer::Statistics) -> Self {
+impl From<EngineStatistics> for MetricsSnapshot {
+    fn from(stats: EngineStatistics) -> Self {
         Self {
-            disk_read_bytes: stats.disk_read_bytes() as _,
-            disk_write_bytes: stats.disk_write_bytes() as _,
-            disk_read_ios: stats.disk_read_ios() as _,
-            disk_write_ios: stats.disk_write_ios() as _,
+            disk_read_bytes: stats.disk_read_bytes,
+            disk_write_bytes: stats.disk_write_bytes,
+            disk_read_ios: stats.disk_read_ios,
+            disk_write_ios: stats.disk_write_ios,
+            flush_dropped_entries: stats.flush_dropped_entries,
+            memory_evictions: stats.memory_evictions,
+            disk_reclaimed_blocks: stats.disk_reclaimed_blocks,
         }
     }
 }
@@ -46,6 +55,13 @@ impl MetricsSnapshot {
             disk_write_bytes: self.disk_write_bytes - other.disk_write_bytes,
             disk_read_ios: self.disk_read_ios - other.disk_read_ios,
             disk_write_ios: self.disk_write_ios - other.disk_write_ios,
+            flush_dropped_entries: self.flush_dropped_entries - other.flush_dropped_entries,
+            memory_evictions: MemoryEvictions {
+                evicted: self.memory_evictions.evicted - other.memory_evictions.evicted,
+                replaced: self.memory_evictions.replaced - other.memory_evictions.replaced,
+                removed: self.memory_evictions.removed - other.memory_evictions.removed,
+            },
+            disk_reclaimed_blocks: self.disk_reclaimed_blocks - other.disk_reclaimed_blocks,
         }
     }
 }
@@ -67,22 +83,73 @@ impl ReportMetricsAction {
         let metrics = GlobalMetrics::get();
 
         let engine = &self.ctx.engine;
-        // foyer will reserve all the space in the disk; the used space is meaningless
-        metrics.storage.used.record(engine.capacity(), &[]);
-        metrics.storage.capacity.record(engine.capacity(), &[]);
+        let storage = &metrics.storage;
+        let usage = engine.usage();
+        storage.used.record(usage.disk_allocated_bytes, &[]);
+        storage.capacity.record(engine.capacity(), &[]);
+        storage.memory_used.record(usage.memory_used_bytes, &[]);
+        storage
+            .memory_capacity
+            .record(usage.memory_capacity_bytes, &[]);
+        storage.memory_entries.record(usage.memory_entries, &[]);
+        storage
+            .block_fill_ratio
+            .record(usage.block_fill_ratio(), &[]);
+        let blocks = usage.disk_blocks;
+        for (state, count) in [
+            (StorageMetrics::BLOCK_CLEAN, blocks.clean),
+            (StorageMetrics::BLOCK_WRITING, blocks.writing),
+            (StorageMetrics::BLOCK_EVICTABLE, blocks.evictable),
+            (StorageMetrics::BLOCK_RECLAIMING, blocks.reclaiming),
+        ] {
+            storage
+                .blocks
+                .record(count, &StorageMetrics::block_state_labels(state));
+        }
 
-        let current = MetricsSnapshot::from(engine.statistics().as_ref());
+        let current = MetricsSnapshot::from(engine.statistics());
         let previous = self.snapshot.load();
         let difference = current.difference(&previous);
         self.snapshot.store(Arc::new(current));
 
-        let io = &metrics.storage.io;
+        let io = &storage.io;
         let read_label = StorageIOMetrics::operation_labels(StorageIOMetrics::OPERATION_READ);
         let write_label = StorageIOMetrics::operation_labels(StorageIOMetrics::OPERATION_WRITE);
         io.bytes.add(difference.disk_read_bytes, &read_label);
         io.bytes.add(difference.disk_write_bytes, &write_label);
         io.count.add(difference.disk_read_ios, &read_label);
         io.count.add(difference.disk_write_ios, &write_label);
+        storage
+            .flush_dropped
+            .add(difference.flush_dropped_entries, &[]);
+
+        let evictions = difference.memory_evictions;
+        for (tier, reason, count) in [
+            (
+                StorageMetrics::TIER_MEMORY,
+                StorageMetrics::EVICTION_CAPACITY,
+                evictions.evicted,
+            ),
+            (
+                StorageMetrics::TIER_MEMORY,
+                StorageMetrics::EVICTION_REPLACE,
+                evictions.replaced,
+            ),
+            (
+                StorageMetrics::TIER_MEMORY,
+                StorageMetrics::EVICTION_REMOVE,
+                evictions.removed,
+            ),
+            (
+                StorageMetrics::TIER_DISK,
+                StorageMetrics::EVICTION_CAPACITY,
+                difference.disk_reclaimed_blocks,
+            ),
+        ] {
+            storage
+                .evictions
+                .add(count, &StorageMetrics::eviction_labels(tier, reason));
+        }
     }
 }
 
@@ -95,3 +162,34 @@ impl fastimer::schedule::SimpleAction for ReportMetricsAction {
         self.do_report().await;
     }
 }
+
+/// Log the hottest keys of the last interval, then decay their counts.
+pub struct ReportHotKeysAction {
+    ctx: Arc<PercasContext>,
+}
+
+impl ReportHotKeysAction {
+    pub fn new(ctx: Arc<PercasContext>) -> Self {
+        ReportHotKeysAction { ctx }
+    }
+}
+
+impl fastimer::schedule::SimpleAction for ReportHotKeysAction {
+    fn name(&self) -> &str {
+        "report_hot_keys"
+    }
+
+    async fn run(&mut self) {
+        let hot_keys = &self.ctx.hot_keys;
+        let top = hot_keys.top();
+        if !top.is_empty() {
+            let keys = top
+                .iter()
+                .map(|k| format!("[{}]={}", k.key, k.count))
+                .collect::<Vec<_>>()
+                .join(", ");
+            log::info!("hot keys on this node: {keys}");
+        }
+        hot_keys.decay();
+    }
+}
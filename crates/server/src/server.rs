@@ -14,20 +14,31 @@
 
 use std::io;
 use std::net::SocketAddr;
+use std::pin::pin;
 use std::sync::Arc;
 use std::time::Duration;
 
 use exn::Result;
 use exn::ResultExt;
+use exn::bail;
+use fastimer::MakeDelayExt;
 use fastimer::schedule::SimpleActionExt;
+use fastrace::Span;
+use fastrace::future::FutureExt as _;
+use fastrace::local::LocalSpan;
+use futures_util::future::select;
 use jiff::Timestamp;
 use mea::shutdown::ShutdownRecv;
 use mea::shutdown::ShutdownSend;
 use mea::waitgroup::WaitGroup;
+use percas_core::EngineStatus;
+use percas_core::Hit;
 use percas_core::Runtime;
 use percas_core::ServerConfig;
+use percas_core::Tier;
 use percas_core::node_file_path;
 use percas_core::timer;
+use percas_gossip::GossipCodec;
 use percas_gossip::GossipError;
 use percas_gossip::GossipFuture;
 use percas_gossip::GossipMessage;
@@ -40,10 +51,12 @@ use percas_metrics::OperationMetrics;
 use poem::Body;
 use poem::EndpointExt;
 use poem::IntoResponse;
+use poem::Request;
 use poem::Response;
 use poem::Route;
 use poem::handler;
 use poem::http::StatusCode;
+use poem::http::header::CONTENT_TYPE;
 use poem::listener::Acceptor;
 use poem::listener::Listener;
 use poem::listener::TcpAcceptor;
@@ -51,7 +64,11 @@ use poem::listener::TcpListener;
 use poem::web::Data;
 use poem::web::Json;
 use poem::web::Path;
+use poem::web::Query;
 use poem::web::headers::ContentType;
+use poem::web::headers::ETag;
+use poem::web::headers::HeaderMapExt;
+use poem::web::headers::IfNoneMatch;
 use serde::Deserialize;
 use serde::Serialize;
 use url::Url;
@@ -59,8 +76,14 @@ use uuid::Uuid;
 
 use crate::PercasContext;
 use crate::ServerError;
+use crate::access_log::AccessLog;
+use crate::hotkeys::HotKey;
+use crate::middleware::AccessLogMiddleware;
 use crate::middleware::ClusterProxyMiddleware;
+use crate::middleware::DiagnosticsMiddleware;
 use crate::middleware::LoggerMiddleware;
+use crate::middleware::TracingMiddleware;
+use crate::scheduled::ReportHotKeysAction;
 use crate::scheduled::ReportMetricsAction;
 
 type ServerFuture<T> = percas_core::JoinHandle<Result<T, ServerError>>;
@@ -160,6 +183,9 @@ pub async fn start_server(
     advertise_ctrl_url: Url,
     gossip_state: Arc<GossipState>,
     gossip_futs: Vec<GossipFuture>,
+    report_interval: Duration,
+    slow_request_threshold: Duration,
+    access_log: Option<Arc<AccessLog>>,
 ) -> Result<ServerState, ServerError> {
     let make_error = || ServerError("failed to start server".to_string());
 
@@ -177,10 +203,16 @@ pub async fn start_server(
                 poem::get(get)
                     .put(put)
                     .delete(delete)
-                    .with(proxy_middleware),
+                    .with(proxy_middleware)
+                    .with(DiagnosticsMiddleware::new(
+                        ctx.clone(),
+                        slow_request_threshold,
+                    ))
+                    .with(AccessLogMiddleware::new(access_log)),
             )
             .data(ctx.clone())
-            .with(LoggerMiddleware);
+            .with(LoggerMiddleware::new(ctx.hash_keys))
+            .with(TracingMiddleware::new(gossip_state.current().node_id));
         let listen_addr = acceptor.local_addr()[0].clone();
         let signal = async move {
             log::info!("server has started on [{listen_addr}]");
@@ -208,9 +240,20 @@ pub async fn start_server(
         rt,
         timer(),
         None,
-        Duration::from_secs(60),
+        report_interval,
     );
     shutdown_tx_actions.push(shutdown_tx);
+    if ctx.hot_keys.is_enabled() {
+        let (shutdown_tx, shutdown_rx) = mea::shutdown::new_pair();
+        ReportHotKeysAction::new(ctx.clone()).schedule_with_fixed_delay(
+            async move { shutdown_rx.is_shutdown().await },
+            rt,
+            timer(),
+            None,
+            report_interval,
+        );
+        shutdown_tx_actions.push(shutdown_tx);
+    }
 
     Ok(ServerState {
         advertise_data_url,
@@ -222,47 +265,97 @@ pub async fn start_server(
     })
 }
 
+/// Load the identity of this node, if it has one.
+///
+/// The id persisted in `node.json` under the node dir wins; a configured `server.node_id` must
+/// agree with it and is used as is before the first start.
+pub fn load_node_id(config: &ServerConfig) -> Result<Option<Uuid>, ServerError> {
+    let node_file_path = node_file_path(&config.dir);
+    let persisted = NodeInfo::load_id(&node_file_path, &config.cluster_id)
+        .or_raise(|| ServerError("failed to load node id".to_string()))?;
+
+    match (persisted, config.node_id) {
+        (Some(persisted), Some(configured)) if persisted != configured => {
+            bail!(ServerError(format!(
+                "server.node_id is {configured}, but {} holds node {persisted}",
+                node_file_path.display()
+            )))
+        }
+        (Some(node_id), _) | (None, Some(node_id)) => Ok(Some(node_id)),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Load the identity of this node from `node.json` in the node dir, or create it with `node_id`.
+///
+/// A loaded node advances its incarnation. Either way, the result is persisted before return.
+pub fn load_or_create_node(
+    config: &ServerConfig,
+    node_id: Uuid,
+    advertise_data_url: Url,
+    advertise_ctrl_url: Url,
+) -> Result<NodeInfo, ServerError> {
+    let node_file_path = node_file_path(&config.dir);
+    let loaded = NodeInfo::load(
+        &node_file_path,
+        &config.cluster_id,
+        advertise_data_url.clone(),
+        advertise_ctrl_url.clone(),
+    )
+    .or_raise(|| ServerError("failed to load node info".to_string()))?;
+
+    let node = if let Some(mut node) = loaded {
+        node.advance_incarnation();
+        node
+    } else {
+        NodeInfo::new(
+            node_id,
+            config.cluster_id.clone(),
+            advertise_data_url,
+            advertise_ctrl_url,
+        )
+    };
+    node.persist(&node_file_path);
+    Ok(node)
+}
+
 pub async fn start_gossip(
     gossip_rt: &Runtime,
     shutdown_rx: ShutdownRecv,
+    ctx: Arc<PercasContext>,
     config: ServerConfig,
-    node_id: Uuid,
+    current_node: NodeInfo,
     acceptor: TcpAcceptor,
-    advertise_data_url: Url,
-    advertise_ctrl_url: Url,
 ) -> Result<(Arc<GossipState>, Vec<GossipFuture>), ServerError> {
     let make_error = || ServerError("failed to start gossip".to_string());
 
     let ServerConfig {
         dir,
         initial_peers,
-        cluster_id,
+        gossip: gossip_config,
         ..
     } = config;
+    let timing = gossip_config.timing().or_raise(make_error)?;
 
-    let node_file_path = node_file_path(&dir);
-    let current_node = if let Some(mut node) = NodeInfo::load(
-        &node_file_path,
-        advertise_data_url.clone(),
-        advertise_ctrl_url.clone(),
-    ) {
-        node.advance_incarnation();
-        node.persist(&node_file_path);
-        node
-    } else {
-        let node = NodeInfo::new(node_id, cluster_id, advertise_data_url, advertise_ctrl_url);
-        node.persist(&node_file_path);
-        node
-    };
-
-    let gossip_state = Arc::new(GossipState::new(current_node, initial_peers, dir));
+    let gossip_state = GossipState::new(current_node, initial_peers, dir, timing)
+        .map(Arc::new)
+        .or_raise(make_error)?;
 
     let route = Route::new()
         .at("/gossip", poem::post(gossip).data(gossip_state.clone()))
         .at(
             "/members",
-            poem::get(list_members).data(gossip_state.clone()),
+            poem::get(list_members)
+                .data(gossip_state.clone())
+                .data(shutdown_rx.clone()),
+        )
+        .at(
+            "/status",
+            poem::get(fetch_status)
+                .data(ctx.clone())
+                .data(gossip_state.clone()),
         )
+        .at("/debug/hotkeys", poem::get(fetch_hot_keys).data(ctx))
         .at("/version", poem::get(fetch_version));
 
     let wg = WaitGroup::new();
@@ -298,6 +391,18 @@ pub async fn start_gossip(
     Ok((gossip_state, gossip_futs))
 }
 
+/// Response header carrying the local epoch of the ring a data request was routed with.
+pub const RING_EPOCH_HEADER: &str = "x-percas-ring-epoch";
+/// Response header carrying the content hash of the ring a data request was routed with.
+pub const RING_HASH_HEADER: &str = "x-percas-ring-hash";
+/// Request header naming the codec of a value written by a typed client, e.g. `json+zstd`.
+pub const CODEC_HEADER: &str = "x-percas-codec";
+/// Request header asking for the `ETag` of a value read, so that a client can cache it and
+/// revalidate it later with `If-None-Match`.
+pub const ETAG_HEADER: &str = "x-percas-etag";
+/// Request header carrying the W3C trace context of the caller.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
 pub fn too_many_requests() -> Response {
     Response::builder()
         .status(StatusCode::TOO_MANY_REQUESTS)
@@ -327,26 +432,81 @@ pub fn get_not_found() -> Response {
         .body(StatusCode::NOT_FOUND.to_string())
 }
 
+fn engine_span(name: &'static str, key: &str) -> Span {
+    Span::enter_with_local_parent(name).with_property(|| ("key_size", key.len().to_string()))
+}
+
+fn tier_name(tier: Tier) -> &'static str {
+    match tier {
+        Tier::Memory => OperationMetrics::TIER_MEMORY,
+        Tier::Disk => OperationMetrics::TIER_DISK,
+    }
+}
+
+/// A strong entity tag of the value, so that clients can revalidate their cached copies.
+fn value_etag(value: &[u8]) -> ETag {
+    let (h1, h2) = mur3::murmurhash3_x64_128(value, 0);
+    format!("\"{h1:016x}{h2:016x}\"")
+        .parse()
+        .expect("hex digits make a valid entity tag")
+}
+
 #[handler]
-pub async fn get(Data(ctx): Data<&Arc<PercasContext>>, key: Path<String>) -> Response {
+pub async fn get(
+    req: &Request,
+    Data(ctx): Data<&Arc<PercasContext>>,
+    key: Path<String>,
+) -> Response {
     let metrics = &GlobalMetrics::get().operation;
     let start = std::time::Instant::now();
 
-    match ctx.engine.get(key.as_bytes()).await {
-        Some(value) => {
-            let labels = OperationMetrics::operation_labels(
-                OperationMetrics::OPERATION_GET,
-                OperationMetrics::STATUS_SUCCESS,
-            );
+    let result = async {
+        let result = ctx.engine.get(key.as_bytes()).await;
+        match &result {
+            Ok(Some(hit)) => LocalSpan::add_properties(|| {
+                [
+                    ("tier", tier_name(hit.tier).to_string()),
+                    ("value_size", hit.value.len().to_string()),
+                ]
+            }),
+            Ok(None) => LocalSpan::add_property(|| ("tier", "miss")),
+            Err(_) => {}
+        }
+        result
+    }
+    .in_span(engine_span("engine.get", &key))
+    .await;
+
+    match result {
+        Ok(Some(Hit { value, tier })) => {
+            let labels = OperationMetrics::hit_labels(tier_name(tier));
             metrics.count.add(1, &labels);
-            metrics.bytes.add(value.len() as u64, &labels);
+            metrics.value_size.record(value.len() as u64, &labels[..1]);
             metrics
                 .duration
                 .record(start.elapsed().as_secs_f64(), &labels);
 
-            get_success(value)
+            // hashing the whole value is only worth it for clients that cache it
+            let if_none_match = req.headers().typed_get::<IfNoneMatch>();
+            let etag = (if_none_match.is_some() || req.headers().contains_key(ETAG_HEADER))
+                .then(|| value_etag(&value));
+            if let Some(etag) = &etag
+                && if_none_match.is_some_and(|cond| !cond.precondition_passes(etag))
+            {
+                return Response::builder()
+                    .status(StatusCode::NOT_MODIFIED)
+                    .typed_header(etag.clone())
+                    .finish();
+            }
+
+            metrics.bytes.add(value.len() as u64, &labels);
+            let mut resp = get_success(value);
+            if let Some(etag) = etag {
+                resp.headers_mut().typed_insert(etag);
+            }
+            resp
         }
-        None => {
+        Ok(None) => {
             let labels = OperationMetrics::operation_labels(
                 OperationMetrics::OPERATION_GET,
                 OperationMetrics::STATUS_NOT_FOUND,
@@ -361,6 +521,19 @@ pub async fn get(Data(ctx): Data<&Arc<PercasContext>>, key: Path<String>) -> Res
                 .typed_header(ContentType::text())
                 .body(StatusCode::NOT_FOUND.to_string())
         }
+        Err(err) => {
+            log::error!("failed to get key [{}]: {err:?}", ctx.log_key(key.as_str()));
+            let labels = OperationMetrics::operation_labels(
+                OperationMetrics::OPERATION_GET,
+                OperationMetrics::STATUS_FAILURE,
+            );
+            metrics.count.add(1, &labels);
+            metrics
+                .duration
+                .record(start.elapsed().as_secs_f64(), &labels);
+
+            internal_server_error()
+        }
     }
 }
 
@@ -371,6 +544,13 @@ pub fn put_success() -> Response {
         .body(StatusCode::CREATED.to_string())
 }
 
+pub fn internal_server_error() -> Response {
+    Response::builder()
+        .status(StatusCode::INTERNAL_SERVER_ERROR)
+        .typed_header(ContentType::text())
+        .body(StatusCode::INTERNAL_SERVER_ERROR.to_string())
+}
+
 pub fn put_bad_request() -> Response {
     Response::builder()
         .status(StatusCode::BAD_REQUEST)
@@ -383,24 +563,41 @@ pub async fn put(Data(ctx): Data<&Arc<PercasContext>>, key: Path<String>, body:
     let metrics = &GlobalMetrics::get().operation;
     let start = std::time::Instant::now();
 
-    match body.into_bytes().await.map(|bytes| {
-        ctx.engine.put(key.as_bytes(), &bytes);
-        bytes.len()
-    }) {
-        Ok(len) => {
+    let bytes = match body.into_bytes().await {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            let labels = OperationMetrics::operation_labels(
+                OperationMetrics::OPERATION_PUT,
+                OperationMetrics::STATUS_FAILURE,
+            );
+            metrics.count.add(1, &labels);
+            metrics
+                .duration
+                .record(start.elapsed().as_secs_f64(), &labels);
+
+            return put_bad_request();
+        }
+    };
+
+    let span =
+        engine_span("engine.put", &key).with_property(|| ("value_size", bytes.len().to_string()));
+    match ctx.engine.put(key.as_bytes(), &bytes).in_span(span).await {
+        Ok(()) => {
             let labels = OperationMetrics::operation_labels(
                 OperationMetrics::OPERATION_PUT,
                 OperationMetrics::STATUS_SUCCESS,
             );
             metrics.count.add(1, &labels);
-            metrics.bytes.add(len as u64, &labels);
+            metrics.bytes.add(bytes.len() as u64, &labels);
+            metrics.value_size.record(bytes.len() as u64, &labels[..1]);
             metrics
                 .duration
                 .record(start.elapsed().as_secs_f64(), &labels);
 
             put_success()
         }
-        Err(_) => {
+        Err(err) => {
+            log::error!("failed to put key [{}]: {err:?}", ctx.log_key(key.as_str()));
             let labels = OperationMetrics::operation_labels(
                 OperationMetrics::OPERATION_PUT,
                 OperationMetrics::STATUS_FAILURE,
@@ -410,7 +607,7 @@ pub async fn put(Data(ctx): Data<&Arc<PercasContext>>, key: Path<String>, body:
                 .duration
                 .record(start.elapsed().as_secs_f64(), &labels);
 
-            put_bad_request()
+            internal_server_error()
         }
     }
 }
@@ -423,28 +620,65 @@ pub fn delete_success() -> Response {
 pub async fn delete(Data(ctx): Data<&Arc<PercasContext>>, key: Path<String>) -> Response {
     let metrics = &GlobalMetrics::get().operation;
     let start = std::time::Instant::now();
-    ctx.engine.delete(key.as_bytes());
+    let result = ctx
+        .engine
+        .delete(key.as_bytes())
+        .in_span(engine_span("engine.delete", &key))
+        .await;
+    let (status, resp) = match result {
+        Ok(()) => (OperationMetrics::STATUS_SUCCESS, delete_success()),
+        Err(err) => {
+            log::error!(
+                "failed to delete key [{}]: {err:?}",
+                ctx.log_key(key.as_str())
+            );
+            (OperationMetrics::STATUS_FAILURE, internal_server_error())
+        }
+    };
 
-    let labels = OperationMetrics::operation_labels(
-        OperationMetrics::OPERATION_DELETE,
-        OperationMetrics::STATUS_SUCCESS,
-    );
+    let labels = OperationMetrics::operation_labels(OperationMetrics::OPERATION_DELETE, status);
     metrics.count.add(1, &labels);
     metrics
         .duration
         .record(start.elapsed().as_secs_f64(), &labels);
 
-    delete_success()
+    resp
 }
 
 #[handler]
-async fn gossip(Json(msg): Json<GossipMessage>, Data(state): Data<&Arc<GossipState>>) -> Response {
+async fn gossip(req: &Request, body: Body, Data(state): Data<&Arc<GossipState>>) -> Response {
+    let Some(codec) = req
+        .header(CONTENT_TYPE)
+        .and_then(GossipCodec::from_content_type)
+    else {
+        return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
+    };
+
+    let msg: GossipMessage = match body.into_bytes().await {
+        Ok(bytes) => match codec.decode(&bytes) {
+            Ok(msg) => msg,
+            Err(err) => {
+                log::warn!("failed to decode gossip message: {err:?}");
+                return StatusCode::BAD_REQUEST.into_response();
+            }
+        },
+        Err(err) => {
+            log::warn!("failed to read gossip message: {err:?}");
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    };
     log::debug!("received message: {msg:?}");
 
-    if let Some(response) = state.handle_message(msg) {
-        Json(response).into_response()
-    } else {
-        ().into_response()
+    match state.handle_message(msg).map(|resp| codec.encode(&resp)) {
+        Some(Ok(body)) => Response::builder()
+            .status(StatusCode::OK)
+            .content_type(codec.content_type())
+            .body(body),
+        Some(Err(err)) => {
+            log::error!("failed to encode gossip message: {err:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        None => ().into_response(),
     }
 }
 
@@ -462,12 +696,44 @@ struct Member {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 struct ListMembersResponse {
+    ring_epoch: u64,
+    ring_hash: u64,
     members: Vec<Member>,
 }
 
+// how long a watch on /members is held when the ring does not change
+const WATCH_MEMBERS_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default, Deserialize)]
+struct ListMembersParams {
+    /// Hold the request until the ring hash differs from this one, or the watch times out.
+    #[serde(default)]
+    watch: Option<u64>,
+}
+
 #[handler]
-async fn list_members(Data(state): Data<&Arc<GossipState>>) -> Response {
+async fn list_members(
+    Data(state): Data<&Arc<GossipState>>,
+    Data(shutdown): Data<&ShutdownRecv>,
+    Query(params): Query<ListMembersParams>,
+) -> Response {
+    if let Some(ring_hash) = params.watch {
+        // subscribe before checking the ring, so that no change is missed in between
+        let mut ring_changes = state.watch_ring();
+        if state.ring().hash == ring_hash {
+            // release the watch on shutdown, or the graceful shutdown would wait for it
+            let changed = pin!(ring_changes.recv());
+            let shutdown = pin!(shutdown.is_shutdown());
+            let _ = timer()
+                .timeout(WATCH_MEMBERS_TIMEOUT, select(changed, shutdown))
+                .await;
+        }
+    }
+
+    let ring = state.ring();
     let resp = ListMembersResponse {
+        ring_epoch: ring.epoch,
+        ring_hash: ring.hash,
         members: state
             .membership()
             .members()
@@ -480,13 +746,46 @@ async fn list_members(Data(state): Data<&Arc<GossipState>>) -> Response {
                 incarnation: m.info.incarnation,
                 status: m.status,
                 heartbeat: m.heartbeat,
-                vnodes: state.ring().list_vnodes(&m.info.node_id),
+                vnodes: ring.ring.list_vnodes(&m.info.node_id),
             })
             .collect(),
     };
     Json(resp).into_response()
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct StatusResponse {
+    node_id: Uuid,
+    storage: EngineStatus,
+}
+
+#[handler]
+async fn fetch_status(
+    Data(ctx): Data<&Arc<PercasContext>>,
+    Data(state): Data<&Arc<GossipState>>,
+) -> Response {
+    let resp = StatusResponse {
+        node_id: state.current().node_id,
+        storage: ctx.engine.status(),
+    };
+    Json(resp).into_response()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct HotKeysResponse {
+    enabled: bool,
+    keys: Vec<HotKey>,
+}
+
+#[handler]
+async fn fetch_hot_keys(Data(ctx): Data<&Arc<PercasContext>>) -> Response {
+    let resp = HotKeysResponse {
+        enabled: ctx.hot_keys.is_enabled(),
+        keys: ctx.hot_keys.top(),
+    };
+    Json(resp).into_response()
+}
+
 #[handler]
 async fn fetch_version() -> Response {
     Json(percas_version::build_info()).into_response()
@@ -496,13 +795,66 @@ async fn fetch_version() -> Response {
 mod tests {
     use insta::assert_json_snapshot;
     use jiff::Timestamp;
+    use percas_core::DeviceStatus;
+    use percas_core::IoEngineKind;
+    use percas_core::IoEngineStatus;
+    use percas_core::StorageEngine;
     use uuid::Uuid;
 
     use super::*;
 
+    #[test]
+    fn test_status_serde() {
+        let resp = StatusResponse {
+            node_id: Uuid::nil(),
+            storage: EngineStatus {
+                engine: StorageEngine::Foyer,
+                capacity: 1024,
+                io_engine: Some(IoEngineStatus {
+                    kind: IoEngineKind::IoUring,
+                    sqpoll: false,
+                    io_depth: 64,
+                    direct: false,
+                }),
+                devices: vec![DeviceStatus {
+                    path: "/mnt/nvme0/percas".into(),
+                    capacity: 1024,
+                    healthy: true,
+                }],
+            },
+        };
+        assert_json_snapshot!(
+            resp,
+            @r#"
+            {
+              "node_id": "00000000-0000-0000-0000-000000000000",
+              "storage": {
+                "engine": "foyer",
+                "capacity": 1024,
+                "io_engine": {
+                  "kind": "io_uring",
+                  "sqpoll": false,
+                  "io_depth": 64,
+                  "direct": false
+                },
+                "devices": [
+                  {
+                    "path": "/mnt/nvme0/percas",
+                    "capacity": 1024,
+                    "healthy": true
+                  }
+                ]
+              }
+            }
+            "#
+        );
+    }
+
     #[test]
     fn test_list_members_serde() {
         let resp = ListMembersResponse {
+            ring_epoch: 7,
+            ring_hash: 42,
             members: vec![Member {
                 node_id: Uuid::nil(),
                 cluster_id: "cluster".to_string(),
@@ -518,6 +870,8 @@ mod tests {
             resp,
             @r#"
             {
+              "ring_epoch": 7,
+              "ring_hash": 42,
               "members": [
                 {
                   "node_id": "00000000-0000-0000-0000-000000000000",
@@ -13,8 +13,11 @@
 // limitations under the License.
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::time::Duration;
 
+use exn::Result;
+use exn::ResultExt;
 use logforth::append;
 use logforth::append::file::FileBuilder;
 use logforth::diagnostic::FastraceDiagnostic;
@@ -23,32 +26,174 @@ use logforth::filter::EnvFilter;
 use logforth::filter::env_filter::EnvFilterBuilder;
 use logforth::layout;
 use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_otlp::WithHttpConfig;
+use opentelemetry_otlp::WithTonicConfig;
+use opentelemetry_otlp::tonic_types::metadata::MetadataMap;
 use percas_core::MetricsConfig;
+use percas_core::OtlpCompression;
+use percas_core::OtlpConfig;
+use percas_core::OtlpProtocol;
 use percas_core::Runtime;
 use percas_core::TelemetryConfig;
 use percas_core::TracesConfig;
+use poem::http::HeaderMap;
+use poem::http::HeaderName;
+use poem::http::HeaderValue;
+
+use crate::ServerError;
 
 pub fn init(
     rt: &Runtime,
     service_name: &'static str,
+    cluster_id: &str,
     node_id: uuid::Uuid,
     config: TelemetryConfig,
 ) -> Vec<Box<dyn Send + Sync + 'static>> {
+    // the logger is installed last, so failures before are reported once it is up
+    let mut warnings = vec![];
+    let resource = make_resource(service_name, cluster_id, node_id, &config);
+    let exporter = match ExporterOptions::new(&config.otlp) {
+        Ok(exporter) => Some(exporter),
+        Err(err) => {
+            warnings.push(format!("failed to configure otlp exporters: {err:?}"));
+            None
+        }
+    };
+
     let mut drop_guards = vec![];
-    if let Some(metrics) = &config.metrics {
-        drop_guards.extend(init_metrics(rt, service_name, metrics));
+    if let (Some(metrics), Some(exporter)) = (&config.metrics, &exporter) {
+        drop_guards.extend(init_metrics(
+            rt,
+            &resource,
+            exporter,
+            metrics,
+            &mut warnings,
+        ));
     }
-    if let Some(traces) = &config.traces {
-        drop_guards.extend(init_traces(rt, service_name, traces));
+    if let (Some(traces), Some(exporter)) = (&config.traces, &exporter) {
+        drop_guards.extend(init_traces(
+            rt,
+            service_name,
+            &resource,
+            exporter,
+            traces,
+            &mut warnings,
+        ));
+    }
+    drop_guards.extend(init_logs(
+        rt,
+        service_name,
+        node_id,
+        &resource,
+        exporter.as_ref(),
+        &config,
+        &mut warnings,
+    ));
+    for warning in warnings {
+        log::warn!("{warning}");
     }
-    drop_guards.extend(init_logs(rt, service_name, node_id, &config));
     drop_guards
 }
 
+fn make_resource(
+    service_name: &'static str,
+    cluster_id: &str,
+    node_id: uuid::Uuid,
+    config: &TelemetryConfig,
+) -> opentelemetry_sdk::Resource {
+    let attributes = [
+        ("service.name", service_name.to_string()),
+        ("cluster_id", cluster_id.to_string()),
+        ("node_id", node_id.to_string()),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v))
+    .chain(config.resource.clone())
+    .map(|(k, v)| opentelemetry::KeyValue::new(k, v));
+    opentelemetry_sdk::Resource::builder()
+        .with_attributes(attributes)
+        .build()
+}
+
+/// Options shared by the OTLP exporters, resolved from [`OtlpConfig`].
+struct ExporterOptions {
+    protocol: OtlpProtocol,
+    headers: HashMap<String, String>,
+    metadata: MetadataMap,
+    timeout: Duration,
+    compression: Option<opentelemetry_otlp::Compression>,
+}
+
+impl ExporterOptions {
+    fn new(config: &OtlpConfig) -> Result<Self, ServerError> {
+        let timeout = config
+            .timeout()
+            .or_raise(|| ServerError("invalid otlp timeout".to_string()))?;
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            let make_error = || ServerError(format!("invalid otlp header {name}"));
+            let name = HeaderName::from_bytes(name.as_bytes()).or_raise(make_error)?;
+            let value = HeaderValue::from_str(value).or_raise(make_error)?;
+            headers.insert(name, value);
+        }
+
+        Ok(Self {
+            protocol: config.protocol,
+            headers: config.headers.clone().into_iter().collect(),
+            metadata: MetadataMap::from_headers(headers),
+            timeout,
+            compression: config.compression.map(|compression| match compression {
+                OtlpCompression::Gzip => opentelemetry_otlp::Compression::Gzip,
+                OtlpCompression::Zstd => opentelemetry_otlp::Compression::Zstd,
+            }),
+        })
+    }
+}
+
+/// Build an OTLP exporter of the given kind with the shared options.
+///
+/// The exporters are driven from threads outside any Tokio runtime: the periodic metrics reader,
+/// the batch log processor and the trace reporter. So HTTP exporters use a blocking client, which
+/// must be built outside the runtime, while gRPC exporters bind their channel to `rt`.
+macro_rules! build_exporter {
+    ($exporter:ty, $endpoint:expr, $options:expr, $rt:expr) => {{
+        let options: &ExporterOptions = $options;
+        match options.protocol {
+            OtlpProtocol::Grpc => {
+                let mut builder = <$exporter>::builder()
+                    .with_tonic()
+                    .with_protocol(opentelemetry_otlp::Protocol::Grpc)
+                    .with_endpoint($endpoint)
+                    .with_timeout(options.timeout)
+                    .with_metadata(options.metadata.clone());
+                if let Some(compression) = options.compression {
+                    builder = builder.with_compression(compression);
+                }
+                $rt.block_on(async move { builder.build() })
+            }
+            OtlpProtocol::HttpProtobuf => {
+                let mut builder = <$exporter>::builder()
+                    .with_http()
+                    .with_protocol(opentelemetry_otlp::Protocol::HttpBinary)
+                    .with_endpoint($endpoint)
+                    .with_timeout(options.timeout)
+                    .with_headers(options.headers.clone());
+                if let Some(compression) = options.compression {
+                    builder = builder.with_compression(compression);
+                }
+                builder.build()
+            }
+        }
+    }};
+}
+
 fn init_metrics(
     rt: &Runtime,
-    service_name: &'static str,
+    resource: &opentelemetry_sdk::Resource,
+    exporter: &ExporterOptions,
     config: &MetricsConfig,
+    warnings: &mut Vec<String>,
 ) -> Vec<Box<dyn Send + Sync + 'static>> {
     let MetricsConfig {
         opentelemetry: Some(config),
@@ -58,39 +203,45 @@ fn init_metrics(
         return vec![];
     };
 
-    rt.block_on(async {
-        let exporter = opentelemetry_otlp::MetricExporter::builder()
-            .with_tonic()
-            .with_protocol(opentelemetry_otlp::Protocol::Grpc)
-            .with_endpoint(&config.otlp_endpoint)
-            .build()
-            .expect("initialize oltp metrics exporter");
-        let reader = opentelemetry_sdk::metrics::PeriodicReader::builder(exporter)
-            .with_interval(Duration::from_secs_f64(config.push_interval.as_secs_f64()))
-            .build();
-        let resource = opentelemetry_sdk::Resource::builder()
-            .with_attributes([opentelemetry::KeyValue::new("service.name", service_name)])
-            .build();
-        let provider = opentelemetry_sdk::metrics::SdkMeterProvider::builder()
-            .with_reader(reader)
-            .with_resource(resource)
-            .build();
+    let exporter = match build_exporter!(
+        opentelemetry_otlp::MetricExporter,
+        &config.otlp_endpoint,
+        exporter,
+        rt
+    ) {
+        Ok(exporter) => exporter,
+        Err(err) => {
+            warnings.push(format!(
+                "failed to initialize otlp metrics exporter; metrics are not exported: {err}"
+            ));
+            return vec![];
+        }
+    };
+    let reader = opentelemetry_sdk::metrics::PeriodicReader::builder(exporter)
+        .with_interval(Duration::from_secs_f64(config.push_interval.as_secs_f64()))
+        .build();
+    let provider = opentelemetry_sdk::metrics::SdkMeterProvider::builder()
+        .with_reader(reader)
+        .with_resource(resource.clone())
+        .build();
 
-        opentelemetry::global::set_meter_provider(provider.clone());
+    opentelemetry::global::set_meter_provider(provider.clone());
 
-        vec![Box::new(scopeguard::guard((), move |_| {
-            provider
-                .shutdown()
-                .inspect_err(|err| log::error!("failed to shutdown metrics provider: {err:?}"))
-                .ok();
-        })) as _]
-    })
+    vec![Box::new(scopeguard::guard((), move |_| {
+        provider
+            .shutdown()
+            .inspect_err(|err| log::error!("failed to shutdown metrics provider: {err:?}"))
+            .ok();
+    })) as _]
 }
 
 fn init_traces(
     rt: &Runtime,
     service_name: &'static str,
+    resource: &opentelemetry_sdk::Resource,
+    exporter: &ExporterOptions,
     config: &TracesConfig,
+    warnings: &mut Vec<String>,
 ) -> Vec<Box<dyn Send + Sync + 'static>> {
     let TracesConfig {
         opentelemetry: Some(opentelemetry),
@@ -100,22 +251,26 @@ fn init_traces(
         return vec![];
     };
 
-    let resource = opentelemetry_sdk::Resource::builder()
-        .with_attributes([opentelemetry::KeyValue::new("service.name", service_name)])
-        .build();
-    let otlp_reporter = rt.block_on(async move {
-        fastrace_opentelemetry::OpenTelemetryReporter::new(
-            opentelemetry_otlp::SpanExporter::builder()
-                .with_tonic()
-                .with_endpoint(&opentelemetry.otlp_endpoint)
-                .with_protocol(opentelemetry_otlp::Protocol::Grpc)
-                .with_timeout(opentelemetry_otlp::OTEL_EXPORTER_OTLP_TIMEOUT_DEFAULT)
-                .build()
-                .expect("initialize oltp trace exporter"),
-            Cow::Owned(resource),
-            opentelemetry::InstrumentationScope::builder(service_name).build(),
-        )
-    });
+    let exporter = build_exporter!(
+        opentelemetry_otlp::SpanExporter,
+        &opentelemetry.otlp_endpoint,
+        exporter,
+        rt
+    );
+    let exporter = match exporter {
+        Ok(exporter) => exporter,
+        Err(err) => {
+            warnings.push(format!(
+                "failed to initialize otlp trace exporter; traces are not exported: {err}"
+            ));
+            return vec![];
+        }
+    };
+    let otlp_reporter = fastrace_opentelemetry::OpenTelemetryReporter::new(
+        exporter,
+        Cow::Owned(resource.clone()),
+        opentelemetry::InstrumentationScope::builder(service_name).build(),
+    );
     fastrace::set_reporter(otlp_reporter, fastrace::collector::Config::default());
 
     vec![Box::new(scopeguard::guard((), |_| {
@@ -132,7 +287,10 @@ fn init_logs(
     rt: &Runtime,
     service_name: &'static str,
     node_id: uuid::Uuid,
+    resource: &opentelemetry_sdk::Resource,
+    exporter: Option<&ExporterOptions>,
     config: &TelemetryConfig,
+    warnings: &mut Vec<String>,
 ) -> Vec<Box<dyn Send + Sync + 'static>> {
     let static_diagnostic = {
         let mut static_diagnostic = StaticDiagnostic::default();
@@ -192,26 +350,33 @@ fn init_logs(
     }
 
     // opentelemetry appender
-    if let Some(opentelemetry) = &config.logs.opentelemetry {
+    if let (Some(opentelemetry), Some(exporter)) = (&config.logs.opentelemetry, exporter) {
         let filter = make_rust_log_filter(&opentelemetry.filter);
-        let appender = rt.block_on(async {
-            let exporter = opentelemetry_otlp::LogExporter::builder()
-                .with_tonic()
-                .with_endpoint(&opentelemetry.otlp_endpoint)
-                .with_protocol(opentelemetry_otlp::Protocol::Grpc)
-                .build()
-                .expect("failed to initialize opentelemetry logger");
-
-            append::opentelemetry::OpentelemetryLogBuilder::new(service_name, exporter)
-                .label("service.name", service_name)
-                .build()
-        });
-        builder = builder.dispatch(|b| {
-            b.filter(filter)
-                .diagnostic(FastraceDiagnostic::default())
-                .diagnostic(static_diagnostic.clone())
-                .append(appender)
-        });
+        let exporter = build_exporter!(
+            opentelemetry_otlp::LogExporter,
+            &opentelemetry.otlp_endpoint,
+            exporter,
+            rt
+        );
+        match exporter {
+            Ok(exporter) => {
+                let mut appender =
+                    append::opentelemetry::OpentelemetryLogBuilder::new(service_name, exporter);
+                for (key, value) in resource.iter() {
+                    appender = appender.label(key.to_string(), value.to_string());
+                }
+                let appender = appender.build();
+                builder = builder.dispatch(|b| {
+                    b.filter(filter)
+                        .diagnostic(FastraceDiagnostic::default())
+                        .diagnostic(static_diagnostic.clone())
+                        .append(appender)
+                });
+            }
+            Err(err) => warnings.push(format!(
+                "failed to initialize otlp log exporter; logs are not exported: {err}"
+            )),
+        }
     }
 
     // apply returns err if already set; ignored
@@ -225,3 +390,60 @@ fn make_rust_log_filter(filter: &str) -> EnvFilter {
         .unwrap_or_else(|_| panic!("failed to parse filter: {filter}"));
     builder.build()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    use opentelemetry::metrics::MeterProvider;
+    use percas_core::OtlpConfig;
+    use percas_core::make_runtime;
+
+    use super::*;
+
+    #[test]
+    fn test_export_over_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1/metrics", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![0; 64 * 1024];
+            let n = stream.read(&mut request).unwrap();
+            tx.send(String::from_utf8_lossy(&request[..n]).to_string())
+                .unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .unwrap();
+        });
+
+        let rt = make_runtime("test_runtime", "test_thread", 1);
+        let options = ExporterOptions::new(&OtlpConfig {
+            protocol: OtlpProtocol::HttpProtobuf,
+            headers: [("x-tenant".to_string(), "percas".to_string())].into(),
+            ..OtlpConfig::default()
+        })
+        .unwrap();
+        let exporter =
+            build_exporter!(opentelemetry_otlp::MetricExporter, &endpoint, &options, rt).unwrap();
+
+        // the periodic reader exports from its own thread, outside any runtime
+        let provider = opentelemetry_sdk::metrics::SdkMeterProvider::builder()
+            .with_reader(opentelemetry_sdk::metrics::PeriodicReader::builder(exporter).build())
+            .build();
+        let counter = provider.meter("test").u64_counter("test.count").build();
+        counter.add(1, &[]);
+        provider.force_flush().unwrap();
+
+        let request = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(request.starts_with("POST /v1/metrics "), "{request}");
+        assert!(request.contains("x-tenant: percas"), "{request}");
+        assert!(
+            request.contains("content-type: application/x-protobuf"),
+            "{request}"
+        );
+    }
+}
@@ -20,16 +20,19 @@ use mea::shutdown::ShutdownSend;
 use percas_client::Client;
 use percas_client::ClientBuilder;
 use percas_core::Config;
-use percas_core::FoyerEngine;
 use percas_core::Runtime;
 use percas_core::ServerConfig;
 use percas_core::StorageConfig;
+use percas_core::StorageEngine;
 use percas_core::TelemetryConfig;
 use percas_core::default_cluster_id;
 use percas_core::default_disk_capacity;
 use percas_core::default_memory_capacity;
 use percas_core::make_runtime;
+use percas_core::open_engine;
+use percas_server::hotkeys::HotKeys;
 use percas_server::server::ServerState;
+use percas_server::server::load_or_create_node;
 use percas_server::server::make_acceptor_and_advertise_url;
 use percas_server::telemetry;
 use uuid::Uuid;
@@ -73,34 +76,51 @@ fn start_test_server(test_name: &str, rt: &Runtime) -> Option<TestServerState> {
             advertise_ctrl_addr: None,
             initial_peers: vec![],
             cluster_id: default_cluster_id(),
+            node_id: None,
+            gossip: Default::default(),
         },
         storage: StorageConfig {
+            engine: StorageEngine::Memory,
             data_dir: temp_dir.path().to_path_buf().join("data"),
             disk_capacity: default_disk_capacity(),
             memory_capacity: default_memory_capacity(),
             disk_throttle: None,
+            io_engine: Default::default(),
+            devices: vec![],
+            faults: None,
         },
         telemetry: TelemetryConfig {
             logs: Default::default(),
             traces: None,
             metrics: None,
+            access_log: None,
+            diagnostics: Default::default(),
+            otlp: Default::default(),
+            resource: Default::default(),
         },
     };
 
-    let mut drop_guards = telemetry::init(rt, service_name, node_id, config.telemetry);
+    let report_interval = config.telemetry.report_interval().unwrap();
+    let slow_request_threshold = config
+        .telemetry
+        .diagnostics
+        .slow_request_threshold()
+        .unwrap();
+    let hash_keys = config.telemetry.diagnostics.hash_keys;
+    let hot_keys = HotKeys::new(config.telemetry.diagnostics.hot_keys, hash_keys);
+    let mut drop_guards = telemetry::init(
+        rt,
+        service_name,
+        &config.server.cluster_id,
+        node_id,
+        config.telemetry,
+    );
     let (shutdown_tx, shutdown_rx) = mea::shutdown::new_pair();
     let server_state = rt.block_on(async move {
-        let engine = FoyerEngine::try_new(
-            rt,
-            &config.storage.data_dir,
-            config.storage.memory_capacity.into(),
-            config.storage.disk_capacity.into(),
-            config.storage.disk_throttle,
-            None,
-        )
-        .await
-        .unwrap();
-        let ctx = Arc::new(percas_server::PercasContext::new(engine));
+        let engine = open_engine(rt, &config.storage, None).await.unwrap();
+        let ctx = Arc::new(percas_server::PercasContext::new(
+            engine, hot_keys, hash_keys,
+        ));
 
         let (data_acceptor, advertise_data_url) =
             make_acceptor_and_advertise_url(listen_addr, None)
@@ -112,14 +132,21 @@ fn start_test_server(test_name: &str, rt: &Runtime) -> Option<TestServerState> {
                 .await
                 .unwrap();
 
+        let current_node = load_or_create_node(
+            &config.server,
+            node_id,
+            advertise_data_url.clone(),
+            advertise_ctrl_url.clone(),
+        )
+        .unwrap();
+
         let (gossip_state, gossip_futs) = percas_server::server::start_gossip(
             rt,
             shutdown_rx.clone(),
+            ctx.clone(),
             config.server,
-            node_id,
+            current_node,
             ctrl_acceptor,
-            advertise_data_url.clone(),
-            advertise_ctrl_url.clone(),
         )
         .await
         .unwrap();
@@ -133,6 +160,9 @@ fn start_test_server(test_name: &str, rt: &Runtime) -> Option<TestServerState> {
             advertise_ctrl_url,
             gossip_state,
             gossip_futs,
+            report_interval,
+            slow_request_threshold,
+            None,
         )
         .await
         .unwrap()
@@ -148,6 +178,8 @@ fn start_test_server(test_name: &str, rt: &Runtime) -> Option<TestServerState> {
 
 pub struct Testkit {
     pub client: Client,
+    pub data_url: String,
+    pub ctrl_url: String,
 }
 
 pub fn harness<T, Fut>(test: impl Send + FnOnce(Testkit) -> Fut) -> ExitCode
@@ -163,14 +195,19 @@ where
     };
 
     rt.block_on(async move {
-        let client = ClientBuilder::new(
-            state.server_state.advertise_data_url().to_string(),
-            state.server_state.advertise_ctrl_url().to_string(),
-        )
-        .build()
-        .unwrap();
-
-        let exit_code = test(Testkit { client }).await.report();
+        let data_url = state.server_state.advertise_data_url().to_string();
+        let ctrl_url = state.server_state.advertise_ctrl_url().to_string();
+        let client = ClientBuilder::new(data_url.clone(), ctrl_url.clone())
+            .build()
+            .unwrap();
+
+        let exit_code = test(Testkit {
+            client,
+            data_url,
+            ctrl_url,
+        })
+        .await
+        .report();
 
         state.shutdown().await;
         exit_code
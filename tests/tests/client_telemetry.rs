@@ -0,0 +1,148 @@
+// Copyright 2025 ScopeDB <contact@scopedb.io>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The metrics and spans of the client. They are process-wide, so this test runs in its own
+//! binary.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use fastrace::collector::Config;
+use fastrace::collector::Reporter;
+use fastrace::collector::SpanContext;
+use fastrace::collector::SpanRecord;
+use fastrace::prelude::*;
+use futures_util::TryStreamExt;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::metrics::InMemoryMetricExporter;
+use opentelemetry_sdk::metrics::PeriodicReader;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::metrics::data::AggregatedMetrics;
+use opentelemetry_sdk::metrics::data::MetricData;
+use opentelemetry_sdk::metrics::data::ResourceMetrics;
+use percas_client::testing::MockServer;
+
+struct TestReporter(Arc<Mutex<Vec<SpanRecord>>>);
+
+impl Reporter for TestReporter {
+    fn report(&mut self, spans: Vec<SpanRecord>) {
+        self.0.lock().unwrap().extend(spans);
+    }
+}
+
+fn has_label<'a>(mut labels: impl Iterator<Item = &'a KeyValue>, key: &str, value: &str) -> bool {
+    labels.any(|kv| kv.key.as_str() == key && kv.value.as_str() == value)
+}
+
+/// The sum of a counter over the data points of an operation.
+fn counter(metrics: &[ResourceMetrics], name: &str, operation: &str) -> u64 {
+    let mut total = 0;
+    for metric in metrics
+        .iter()
+        .flat_map(|rm| rm.scope_metrics())
+        .flat_map(|sm| sm.metrics())
+        .filter(|metric| metric.name() == name)
+    {
+        if let AggregatedMetrics::U64(MetricData::Sum(sum)) = metric.data() {
+            total += sum
+                .data_points()
+                .filter(|point| has_label(point.attributes(), "operation", operation))
+                .map(|point| point.value())
+                .sum::<u64>();
+        }
+    }
+    total
+}
+
+/// The longest duration of an operation, in seconds.
+fn max_duration(metrics: &[ResourceMetrics], operation: &str) -> f64 {
+    let mut max = 0.0_f64;
+    for metric in metrics
+        .iter()
+        .flat_map(|rm| rm.scope_metrics())
+        .flat_map(|sm| sm.metrics())
+        .filter(|metric| metric.name() == "percas.client.request.duration")
+    {
+        if let AggregatedMetrics::F64(MetricData::Histogram(histogram)) = metric.data() {
+            for point in histogram
+                .data_points()
+                .filter(|point| has_label(point.attributes(), "operation", operation))
+            {
+                max = max.max(point.max().unwrap_or_default());
+            }
+        }
+    }
+    max
+}
+
+#[tokio::test]
+async fn test_client_metrics_and_spans() {
+    let exporter = InMemoryMetricExporter::default();
+    let provider = SdkMeterProvider::builder()
+        .with_reader(PeriodicReader::builder(exporter.clone()).build())
+        .build();
+    opentelemetry::global::set_meter_provider(provider.clone());
+    let spans = Arc::new(Mutex::new(vec![]));
+    fastrace::set_reporter(TestReporter(spans.clone()), Config::default());
+
+    let server = MockServer::start().await.unwrap();
+    let client = server.client_builder().build().unwrap();
+    let root = Span::root("test", SpanContext::random());
+    async {
+        client.put("key", b"value").await.unwrap();
+        assert_eq!(client.get("key").await.unwrap().unwrap(), b"value");
+
+        // the request is recorded once its value is read
+        let stream = client.get_stream("key").await.unwrap().unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let chunks = stream.try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(chunks.concat(), b"value");
+    }
+    .in_span(root)
+    .await;
+
+    provider.force_flush().unwrap();
+    let metrics = exporter.get_finished_metrics().unwrap();
+    assert_eq!(counter(&metrics, "percas.client.request.count", "put"), 1);
+    assert_eq!(counter(&metrics, "percas.client.request.count", "get"), 2);
+    assert_eq!(counter(&metrics, "percas.client.request.bytes", "put"), 5);
+    assert_eq!(counter(&metrics, "percas.client.request.bytes", "get"), 10);
+    assert!(max_duration(&metrics, "get") >= 0.2);
+
+    fastrace::flush();
+    let spans = spans.lock().unwrap();
+    let names = spans
+        .iter()
+        .map(|span| span.name.as_ref())
+        .collect::<Vec<_>>();
+    for name in ["client.put", "client.get", "client.attempt"] {
+        assert!(names.contains(&name), "{names:?}");
+    }
+    let attempts = spans
+        .iter()
+        .filter(|span| span.name == "client.attempt")
+        .collect::<Vec<_>>();
+    assert_eq!(attempts.len(), 3);
+    for attempt in attempts {
+        let properties = attempt
+            .properties
+            .iter()
+            .map(|(key, value)| (key.as_ref(), value.as_ref()))
+            .collect::<Vec<_>>();
+        for key in ["node", "http.status_code"] {
+            assert!(properties.iter().any(|(k, _)| *k == key), "{properties:?}");
+        }
+    }
+}
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::time::Duration;
+
 use behavior_tests::Testkit;
 use behavior_tests::harness;
 use behavior_tests::render_hex;
@@ -48,3 +50,195 @@ async fn test_put_get(testkit: Testkit) {
         "
     );
 }
+
+#[test(harness)]
+async fn test_unreachable_server(_testkit: Testkit) {
+    // reserve a port and release it so that nothing listens there
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let client =
+        percas_client::ClientBuilder::new(format!("http://{addr}"), format!("http://{addr}"))
+            .build()
+            .unwrap();
+
+    // without a route table, requests go to the data server url
+    let err = client.get("key").await.unwrap_err();
+    assert!(err.is_retryable(), "{err:?}");
+    assert_eq!(err.url().unwrap().path(), "/key");
+    assert!(
+        matches!(err, percas_client::Error::Connect { .. }),
+        "{err:?}"
+    );
+}
+
+#[test(harness)]
+async fn test_fallback_ctrl_url(testkit: Testkit) {
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let client = percas_client::ClientBuilder::new(testkit.data_url, format!("http://{addr}"))
+        .add_ctrl_url(testkit.ctrl_url)
+        .watch_members(true)
+        .build()
+        .unwrap();
+
+    client.put("key", b"value").await.unwrap();
+    let value = client.get("key").await.unwrap().unwrap();
+    assert_eq!(value, b"value");
+}
+
+#[test(harness)]
+async fn test_call_deadline(_testkit: Testkit) {
+    // a listener that accepts connections but never responds
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client =
+        percas_client::ClientBuilder::new(format!("http://{addr}"), format!("http://{addr}"))
+            .build()
+            .unwrap();
+
+    let timeout = Duration::from_millis(200);
+    let options = percas_client::RequestOptions::new().with_timeout(timeout);
+    let err = client.get_with("key", &options).await.unwrap_err();
+    assert!(err.is_retryable(), "{err:?}");
+    let percas_client::Error::DeadlineExceeded {
+        timeout: actual, ..
+    } = err
+    else {
+        panic!("unexpected error: {err:?}");
+    };
+    assert_eq!(actual, timeout);
+}
+
+#[test(harness)]
+async fn test_near_cache(testkit: Testkit) {
+    let client = percas_client::ClientBuilder::new(testkit.data_url, testkit.ctrl_url)
+        .near_cache(percas_client::NearCachePolicy::new(1024).with_max_age(Duration::ZERO))
+        .build()
+        .unwrap();
+
+    client.put("key", b"value").await.unwrap();
+    // a miss, then a revalidation the server confirms with the etag
+    for _ in 0..2 {
+        let value = client.get("key").await.unwrap().unwrap();
+        assert_eq!(value, b"value");
+    }
+    let stats = client.near_cache_stats().unwrap();
+    assert_eq!((stats.misses, stats.revalidations), (1, 1));
+    assert_eq!((stats.not_modified, stats.entries), (1, 1));
+
+    // a local write invalidates the entry
+    client.put("key", b"other").await.unwrap();
+    assert_eq!(client.near_cache_stats().unwrap().entries, 0);
+    let value = client.get("key").await.unwrap().unwrap();
+    assert_eq!(value, b"other");
+
+    let options = percas_client::RequestOptions::new().with_bypass_near_cache(true);
+    client.get_with("key", &options).await.unwrap().unwrap();
+    let stats = client.near_cache_stats().unwrap();
+    assert_eq!((stats.hits, stats.misses, stats.revalidations), (0, 2, 1));
+
+    client.delete("key").await.unwrap();
+    assert_eq!(client.get("key").await.unwrap(), None);
+}
+
+#[test(harness)]
+async fn test_blocking_client(testkit: Testkit) {
+    let (data_url, ctrl_url) = (testkit.data_url, testkit.ctrl_url);
+    std::thread::spawn(move || {
+        let client = percas_client::ClientBuilder::new(data_url, ctrl_url)
+            .build_blocking()
+            .unwrap();
+        client.version().unwrap();
+
+        client.put("key", b"value").unwrap();
+        assert_eq!(client.get("key").unwrap().unwrap(), b"value");
+        client.delete("key").unwrap();
+        assert_eq!(client.get("key").unwrap(), None);
+    })
+    .join()
+    .unwrap();
+}
+
+#[test(harness)]
+async fn test_stream_put_get(testkit: Testkit) {
+    use futures_util::TryStreamExt;
+    use tokio::io::AsyncReadExt;
+
+    let value = (0..1024 * 1024).map(|i| i as u8).collect::<Vec<_>>();
+    let client = testkit.client;
+
+    // from a reader with a known length
+    let reader = std::io::Cursor::new(value.clone());
+    client
+        .put_reader("key", reader, Some(value.len() as u64))
+        .await
+        .unwrap();
+    let stream = client.get_stream("key").await.unwrap().unwrap();
+    assert_eq!(stream.content_length(), Some(value.len() as u64));
+    let chunks = stream.try_collect::<Vec<_>>().await.unwrap();
+    assert_eq!(chunks.concat(), value);
+
+    // from a stream of unknown length
+    let chunks = value
+        .chunks(4096)
+        .map(|chunk| Ok::<_, std::io::Error>(chunk.to_vec()))
+        .collect::<Vec<_>>();
+    client
+        .put_stream("other", futures_util::stream::iter(chunks), None)
+        .await
+        .unwrap();
+    let stream = client.get_stream("other").await.unwrap().unwrap();
+    let mut actual = vec![];
+    stream
+        .into_async_read()
+        .read_to_end(&mut actual)
+        .await
+        .unwrap();
+    assert_eq!(actual, value);
+
+    assert!(client.get_stream("missing").await.unwrap().is_none());
+}
+
+#[test(harness)]
+async fn test_typed_put_get(testkit: Testkit) {
+    use percas_client::Codec;
+    use percas_client::Compression;
+    use percas_client::Format;
+
+    let value = vec![("percas".to_string(), 42u64); 64];
+    testkit.client.put_typed("key", &value).await.unwrap();
+    let raw = testkit.client.get("key").await.unwrap().unwrap();
+    assert_eq!(&raw[..4], b"PC\x01\x00");
+
+    // values are decoded with the codec they were written with
+    let codec = Codec::new(Format::Postcard).with_compression(Compression::Zstd);
+    let client = percas_client::ClientBuilder::new(testkit.data_url, testkit.ctrl_url)
+        .codec(codec)
+        .build()
+        .unwrap();
+    let actual: Vec<(String, u64)> = client.get_typed("key").await.unwrap().unwrap();
+    assert_eq!(actual, value);
+    client.put_typed("key", &value).await.unwrap();
+    let raw = client.get("key").await.unwrap().unwrap();
+    assert_eq!(&raw[..4], b"PC\x03\x01");
+    let actual: Vec<(String, u64)> = testkit.client.get_typed("key").await.unwrap().unwrap();
+    assert_eq!(actual, value);
+
+    client.put("key", b"untyped").await.unwrap();
+    let err = client.get_typed::<String>("key").await.unwrap_err();
+    assert!(
+        matches!(err, percas_client::Error::Deserialize { .. }),
+        "{err}"
+    );
+    assert!(
+        client
+            .get_typed::<String>("missing")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}